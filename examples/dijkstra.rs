@@ -28,13 +28,11 @@
 //! ```
 extern crate hollow_heap;
 
-use hollow_heap::HollowHeap;
+use hollow_heap::IndexedHollowHeap;
 
 use std::collections::HashSet;
 use std::collections::VecDeque;
 
-use std::cmp::Ordering;
-
 /// A node in the graph, made up a any content type `T` and a `Vec` of vertices
 pub struct Node<T> {
     /// content can be any type `T`
@@ -44,8 +42,7 @@ pub struct Node<T> {
 }
 
 /// A vertex between two `Node`s with an associated `i32` cost and a target node.
-/// `Vertex` derives `Copy`, `Debug`, `Eq` and `PartialEq` and implements `Ord` and
-/// `PartialOrd` as we use it ordered compound types.
+/// `Vertex` derives `Copy`, `Debug`, `Eq` and `PartialEq`.
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub struct Vertex {
     /// cost is defiened as an `ì32`, which might change
@@ -53,23 +50,6 @@ pub struct Vertex {
     /// node, an `usize` index of the node at the other end of this vertex
     pub node: usize,
 }
-// The priority queue depends on `Ord`.
-// Explicitly implement the trait so the queue becomes a min-heap
-// instead of a max-heap.
-impl Ord for Vertex {
-    fn cmp(&self, other: &Vertex) -> Ordering {
-        // Notice that the we flip the ordering here
-        other.cost.cmp(&self.cost)
-        //self.cost.cmp(&other.cost)
-    }
-}
-
-// `PartialOrd` needs to be implemented as well.
-impl PartialOrd for Vertex {
-    fn partial_cmp(&self, other: &Vertex) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
 
 /// A graph, represeted by as a weighted
 /// [Adjacency list](http://en.wikipedia.org/wiki/Adjacency_list) of `Node`s
@@ -86,8 +66,8 @@ impl<T: PartialEq> Graph<T> {
     ///
     /// ## Arguments
     /// * `input` - an adjacency list in made out of a `Vec` of Nodes.
-    ///    Weights are represented as `i32`:s and can thus be positive or
-    ///    negative numbers.
+    ///   Weights are represented as `i32`:s and can thus be positive or
+    ///   negative numbers.
     ///
     /// ## Example
     /// ```
@@ -129,7 +109,7 @@ impl<T: PartialEq> Graph<T> {
     /// Either the found path between start and target as a `VecDeque` of `usize`:s
     /// or `None` if there is no path.
     pub fn search(&self, start: usize, target: T) -> Option<VecDeque<usize>> {
-        return self.dijkstra(start, &target);
+        self.dijkstra(start, &target)
     }
 
     /// `search_using_index` promises to use a correct method, i.e. one which will
@@ -147,7 +127,7 @@ impl<T: PartialEq> Graph<T> {
     /// Either the found path between start and target as a `VecDeque` of `usize`:s
     /// or `None` if there is no path.
     pub fn search_using_index(&self, start: usize, target: usize) -> Option<VecDeque<usize>> {
-        return self.dijkstra_using_index(start, target);
+        self.dijkstra_using_index(start, target)
     }
 
     /// `breadth_first_search` implements breadth first search from `start` to the
@@ -165,7 +145,7 @@ impl<T: PartialEq> Graph<T> {
     /// Either the found path between start and target as a `VecDeque` of `usize`:s
     /// or `None` if there is no path.
     pub fn breadth_first_search(&self, start: usize, target: T) -> Option<VecDeque<usize>> {
-        return self.inner_search(start, &target, true);
+        self.inner_search(start, &target, true)
     }
 
     /// `breadth_first_search` implements breadth first search from the `start` index to the
@@ -187,7 +167,7 @@ impl<T: PartialEq> Graph<T> {
         start: usize,
         target: usize,
     ) -> Option<VecDeque<usize>> {
-        return self.inner_search_using_index(start, target, true);
+        self.inner_search_using_index(start, target, true)
     }
 
     /// `depth_first_search` implements depth first search from `start` to the
@@ -205,7 +185,7 @@ impl<T: PartialEq> Graph<T> {
     /// Either the found path between start and target as a `VecDeque` of `usize`:s
     /// or `None` if there is no path.
     pub fn depth_first_search(&self, start: usize, target: T) -> Option<VecDeque<usize>> {
-        return self.inner_search(start, &target, false);
+        self.inner_search(start, &target, false)
     }
 
     /// `depth_first_search_using_index` implements depth first search from the `start`
@@ -227,7 +207,7 @@ impl<T: PartialEq> Graph<T> {
         start: usize,
         target: usize,
     ) -> Option<VecDeque<usize>> {
-        return self.inner_search_using_index(start, target, false);
+        self.inner_search_using_index(start, target, false)
     }
 
     /// `dijkstra` implements Dijkstras algorithm for search from `start` to the
@@ -242,40 +222,31 @@ impl<T: PartialEq> Graph<T> {
     /// Either the found path between start and target as a `VecDeque` of `usize`:s
     /// or `None` if there is no path.
     pub fn dijkstra(&self, start: usize, target: &T) -> Option<VecDeque<usize>> {
-        let mut q = HollowHeap::max_heap();
-        let mut costs: Vec<_> = (0..self.graph.len()).map(|_| std::i32::MAX).collect();
+        let mut q = IndexedHollowHeap::min_heap(self.graph.len());
+        let mut costs: Vec<_> = (0..self.graph.len()).map(|_| i32::MAX).collect();
         let mut prev: Vec<usize> = (0..self.graph.len()).map(|_| 0).collect();
         let mut pathfound = false;
         let mut target_index = start;
 
         costs[start] = 0;
-        q.push(Vertex {
-            cost: 0,
-            node: start,
-        });
+        q.insert(start, 0).expect("The queue starts out empty.");
 
-        while !q.is_empty() {
-            let v = q.pop();
-            match v {
-                None => {}
-                Some(Vertex { cost, node }) => {
-                    if &self.graph[node].content == target {
-                        pathfound = true;
-                        target_index = node
-                    }
-                    if cost > costs[node] {
-                        continue;
-                    } // we have a better path
-                    for vert in &self.graph[node].adjacent {
-                        let next = Vertex {
-                            cost: cost + vert.cost,
-                            node: vert.node,
-                        };
-                        if next.cost < costs[vert.node] {
-                            costs[vert.node] = next.cost;
-                            prev[vert.node] = node;
-                            q.push(next);
-                        }
+        while let Some((node, cost)) = q.pop() {
+            if &self.graph[node].content == target {
+                pathfound = true;
+                target_index = node
+            }
+            for vert in &self.graph[node].adjacent {
+                let next_cost = cost + vert.cost;
+                if next_cost < costs[vert.node] {
+                    costs[vert.node] = next_cost;
+                    prev[vert.node] = node;
+                    if q.contains(vert.node) {
+                        q.decrease_key(vert.node, next_cost)
+                            .expect("The cost only ever decreases.");
+                    } else {
+                        q.insert(vert.node, next_cost)
+                            .expect("The node is not in the queue.");
                     }
                 }
             }
@@ -283,7 +254,7 @@ impl<T: PartialEq> Graph<T> {
         if pathfound {
             return Some(self.backtrack(prev, start, target_index));
         }
-        return None;
+        None
     }
 
     /// `dijkstra_using_index` implements Dijkstras algorithm for search from the `start`
@@ -299,8 +270,8 @@ impl<T: PartialEq> Graph<T> {
     /// or `None` if there is no path.
     pub fn dijkstra_using_index(&self, start: usize, target: usize) -> Option<VecDeque<usize>> {
         match self.index_to_node(target) {
-            None => return None, // target does not exist in the graph
-            Some(node) => return self.dijkstra(start, &node.content),
+            None => None, // target does not exist in the graph
+            Some(node) => self.dijkstra(start, &node.content),
         }
     }
     /// `cost_of_path` takes a path returned from any of the search functions and
@@ -308,7 +279,7 @@ impl<T: PartialEq> Graph<T> {
     ///
     /// ## Arguments
     /// * `path` - a borrowed reference to a `VecDeque<usize>` representing a path
-    ///            through the graph
+    ///   through the graph
     ///
     /// ## Returns
     /// The cost of traversing said path represented as an `i32`
@@ -317,11 +288,11 @@ impl<T: PartialEq> Graph<T> {
         for i in 0..path.len() - 1 {
             for vert in &self.graph[path[i]].adjacent {
                 if vert.node == path[i + 1] {
-                    cost = cost + vert.cost;
+                    cost += vert.cost;
                 }
             }
         }
-        return cost;
+        cost
     }
 
     /// `node_to_index` takes an `Node` (from the graph) and returns its index, if
@@ -337,12 +308,7 @@ impl<T: PartialEq> Graph<T> {
     /// An Optional index, either `None` if the node does not exist in the graph or
     /// `Some(index)`
     pub fn node_to_index(&self, node: Node<T>) -> Option<usize> {
-        for i in 0..self.graph.len() {
-            if node.content == self.graph[i].content {
-                return Some(i);
-            }
-        }
-        return None;
+        (0..self.graph.len()).find(|&i| node.content == self.graph[i].content)
     }
 
     /// `index_to_node` takes an index (from the graph) and returns the node at the
@@ -359,7 +325,7 @@ impl<T: PartialEq> Graph<T> {
         if index < self.graph.len() {
             return Some(&self.graph[index]);
         }
-        return None;
+        None
     }
 
     fn inner_search(&self, start: usize, target: &T, bfs: bool) -> Option<VecDeque<usize>> {
@@ -398,7 +364,7 @@ impl<T: PartialEq> Graph<T> {
         if pathfound {
             return Some(self.backtrack(prev, start, target_index));
         }
-        return None;
+        None
     }
 
     fn inner_search_using_index(
@@ -408,8 +374,8 @@ impl<T: PartialEq> Graph<T> {
         bfs: bool,
     ) -> Option<VecDeque<usize>> {
         match self.index_to_node(target) {
-            None => return None, // target does not exist in the graph
-            Some(node) => return self.inner_search(start, &node.content, bfs),
+            None => None, // target does not exist in the graph
+            Some(node) => self.inner_search(start, &node.content, bfs),
         }
     }
 
@@ -427,7 +393,7 @@ impl<T: PartialEq> Graph<T> {
             curr = prev[curr];
         }
         path.push_front(start);
-        return path;
+        path
     }
 }
 
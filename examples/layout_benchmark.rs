@@ -0,0 +1,57 @@
+//! Compares the node layouts of the `HollowHeap` for different item sizes.
+//!
+//! Run once with `cargo run --release --example layout_benchmark` for the default layout and once
+//! with `--features interleaved_layout` added for the interleaved one.
+
+extern crate hollow_heap;
+
+use std::time::{Duration, Instant};
+
+use hollow_heap::HollowHeap;
+
+const ITEMS: u64 = 200_000;
+const ROUNDS: u32 = 5;
+
+/// A pseudo random but reproducible sequence of keys.
+fn key(i: u64) -> u64 {
+    i.wrapping_mul(6_364_136_223_846_793_005).rotate_left(17) % 1_000_000_000 + 1
+}
+
+/// Push `ITEMS` values, improve the key of every other one and pop everything.
+fn run<V: Clone>(payload: V) -> Duration {
+    let mut heap: HollowHeap<u64, (u64, V)> = HollowHeap::new(|lhs, rhs| lhs < rhs, |val| val.0);
+    let start = Instant::now();
+    let mut handles = Vec::with_capacity(ITEMS as usize);
+    for i in 0..ITEMS {
+        handles.push(heap.push((key(i), payload.clone())));
+    }
+    for (i, handle) in handles.into_iter().enumerate().step_by(2) {
+        heap.change_key(handle, key(i as u64) / 2);
+    }
+    let mut popped = 0;
+    while heap.pop().is_some() {
+        popped += 1;
+    }
+    assert_eq!(popped, ITEMS);
+    start.elapsed()
+}
+
+fn best_of<F: Fn() -> Duration>(f: F) -> Duration {
+    (0..ROUNDS).map(|_| f()).min().unwrap()
+}
+
+fn main() {
+    if cfg!(feature = "interleaved_layout") {
+        println!("layout: items interleaved with keys and links");
+    } else {
+        println!("layout: keys and links apart from the items");
+    }
+    println!(
+        "push {} items, change_key on half of them, pop all (best of {}):",
+        ITEMS, ROUNDS
+    );
+    println!("  payload   8 bytes: {:?}", best_of(|| run(0u64)));
+    println!("  payload  64 bytes: {:?}", best_of(|| run([0u64; 8])));
+    println!("  payload 256 bytes: {:?}", best_of(|| run([0u64; 32])));
+    println!("  payload  1 KiB:    {:?}", best_of(|| run([0u64; 128])));
+}
@@ -18,7 +18,7 @@ fn example_one() {
     println!("{:?}", heap.pop());
     println!("{:?}", heap.pop());
     println!("{:?}", heap.pop());
-    println!("");
+    println!();
 }
 
 fn example_min_heap() {
@@ -37,7 +37,7 @@ fn example_min_heap() {
     println!("{:?}", heap.pop());
     println!("{:?}", heap.pop());
     println!("{:?}", heap.pop());
-    println!("");
+    println!();
 }
 
 fn example_vec() {
@@ -46,7 +46,7 @@ fn example_vec() {
     let mut heap = HollowHeap::max_heap();
     {
         for num in my_vec.iter() {
-            heap.push(num.clone());
+            heap.push(*num);
         }
     }
     {
@@ -54,13 +54,13 @@ fn example_vec() {
     }
     {
         for num in my_vec.iter() {
-            let number = Some(num.clone());
+            let number = Some(*num);
             let top = heap.pop();
             assert!(number == top);
             println!("{:?} == {:?}", number, top);
         }
     }
-    println!("");
+    println!();
 }
 
 fn example_increase() {
@@ -74,7 +74,7 @@ fn example_increase() {
     while let Some(node) = heap.pop() {
         println!("{:?}", node);
     }
-    println!("");
+    println!();
 }
 
 fn example_complicated() {
@@ -85,26 +85,26 @@ fn example_complicated() {
     {
         for num in vec1.into_iter() {
             if num == 666 {
-                five = Some(heap.push(num.clone()));
+                five = Some(heap.push(num));
             } else {
-                heap.push(num.clone());
+                heap.push(num);
             }
         }
     }
     heap.change_key(five.unwrap(), 777);
     println!("{:?}", heap.pop());
     println!("{:?}", heap.pop());
-    let vec2 = vec![2, -55, 67, 110];
+    let vec2 = [2, -55, 67, 110];
     {
         for num in vec2.iter() {
-            let index = heap.push(num.clone());
-            heap.change_key(index, num.clone() + 20);
+            let index = heap.push(*num);
+            heap.change_key(index, *num + 20);
         }
     }
     while let Some(node) = heap.pop() {
         println!("{:?}", node);
     }
-    println!("");
+    println!();
 }
 
 fn example_builder() {
@@ -0,0 +1,250 @@
+//! A hollow heap whose elements are addressed by a user provided id instead of a `Handle`.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::store::Dag;
+use crate::{max_heap_compare, min_heap_compare, Handle, HollowHeap, UpdateError};
+
+/// What `AddressableHollowHeap::push_or_improve` did with the pushed value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// No element had the id, so the value was pushed.
+    Inserted,
+    /// The element with the id was replaced by the value, whose key is 'better'.
+    Improved,
+    /// The element with the id has a key at least as good, so the value was dropped.
+    Unchanged,
+}
+
+/// A hollow heap that keeps track of the handle of every element by its id.
+///
+/// Every element is pushed with an id that is unique within the heap. Keys and items can then be
+/// changed and elements removed by id, without having to keep the handles returned by `push` and
+/// `change_key` up to date. Only available with the `std` feature.
+///
+/// Ids have to be `Clone`, as every id is stored twice: in the map from ids to handles, and next
+/// to the value in the heap, so that `pop` can tell which entry of the map to remove. Use cheap
+/// ids like integers, or wrap expensive ones in an `Rc`.
+///
+/// ```rust
+/// use hollow_heap::AddressableHollowHeap;
+///
+/// let mut jobs: AddressableHollowHeap<&str, u32, u32> = AddressableHollowHeap::min_heap();
+/// jobs.push("backup", 30).unwrap();
+/// jobs.push("deploy", 20).unwrap();
+/// jobs.change_key_by_id(&"backup", 10).unwrap();
+/// assert_eq!(jobs.remove_by_id(&"deploy"), Some(20));
+/// assert_eq!(jobs.pop(), Some(("backup", 30)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct AddressableHollowHeap<Id, K, V> {
+    inner: HollowHeap<K, (Id, V)>,
+    handles: HashMap<Id, Handle>,
+    derive_key: fn(&V) -> K,
+}
+
+impl<Id: Hash + Eq + Clone, K: PartialOrd, V> AddressableHollowHeap<Id, K, V> {
+    pub fn new(
+        compare: fn(&K, &K) -> bool,
+        derive_key: fn(&V) -> K,
+    ) -> AddressableHollowHeap<Id, K, V> {
+        AddressableHollowHeap::with_dag(Dag::new(), HashMap::new(), compare, derive_key)
+    }
+
+    /// Create a new empty heap that is able to hold `n` elements without further allocation.
+    pub fn with_capacity(
+        compare: fn(&K, &K) -> bool,
+        derive_key: fn(&V) -> K,
+        n: usize,
+    ) -> AddressableHollowHeap<Id, K, V> {
+        AddressableHollowHeap::with_dag(
+            Dag::with_capacity(n),
+            HashMap::with_capacity(n),
+            compare,
+            derive_key,
+        )
+    }
+
+    fn with_dag(
+        dag: Dag<K, (Id, V)>,
+        handles: HashMap<Id, Handle>,
+        compare: fn(&K, &K) -> bool,
+        derive_key: fn(&V) -> K,
+    ) -> AddressableHollowHeap<Id, K, V> {
+        AddressableHollowHeap {
+            inner: HollowHeap::with_dag(dag, compare, None),
+            handles,
+            derive_key,
+        }
+    }
+
+    /// Test whether there are any elements in the heap.
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// The number of elements in the heap.
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Push a value with the given id into the heap.
+    ///
+    /// Returns the value back if the heap already has an element with the same id.
+    pub fn push(&mut self, id: Id, value: V) -> Result<(), V> {
+        if self.handles.contains_key(&id) {
+            return Err(value);
+        }
+        let key = (self.derive_key)(&value);
+        let handle = self.inner.push_with_key((id.clone(), value), key);
+        self.handles.insert(id, handle);
+        Ok(())
+    }
+
+    /// Push a value with the given id, or replace the element with the same id if the new key is
+    /// 'better' according to the heap's compare function.
+    pub fn push_or_improve(&mut self, id: Id, value: V) -> Outcome {
+        let handle = match self.handles.get_mut(&id) {
+            Some(handle) => handle,
+            None => {
+                let key = (self.derive_key)(&value);
+                let handle = self.inner.push_with_key((id.clone(), value), key);
+                self.handles.insert(id, handle);
+                return Outcome::Inserted;
+            }
+        };
+        let new_key = (self.derive_key)(&value);
+        match self.inner.update(*handle, Some((id, value)), new_key) {
+            Ok(new_handle) => {
+                *handle = new_handle;
+                Outcome::Improved
+            }
+            Err(UpdateError::NotBetter) => Outcome::Unchanged,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Test whether the heap has an element with the given id.
+    pub fn contains_id(&self, id: &Id) -> bool {
+        self.handles.contains_key(id)
+    }
+
+    /// The value of the element with the given id.
+    pub fn get_by_id(&self, id: &Id) -> Option<&V> {
+        let handle = self.handles.get(id)?;
+        self.inner.get(*handle).map(|(_, value)| value)
+    }
+
+    /// Change the key of the element with the given id to a 'better' one.
+    ///
+    /// **Note:** This function only changes the key, not the item.
+    pub fn change_key_by_id(&mut self, id: &Id, new_key: K) -> Result<(), UpdateError> {
+        let handle = self.handles.get_mut(id).ok_or(UpdateError::UnknownId)?;
+        *handle = self.inner.try_change_key(*handle, new_key)?;
+        Ok(())
+    }
+
+    /// Change the item (and recalculate the key) of the element with the given id. The new key
+    /// has to be 'better' than the old one.
+    ///
+    /// `new_item` is dropped if an error is returned.
+    pub fn change_item_by_id(&mut self, id: &Id, new_item: V) -> Result<(), UpdateError> {
+        let handle = self.handles.get_mut(id).ok_or(UpdateError::UnknownId)?;
+        let new_key = (self.derive_key)(&new_item);
+        let id = id.clone();
+        *handle = self.inner.update(*handle, Some((id, new_item)), new_key)?;
+        Ok(())
+    }
+
+    /// Remove the element with the given id from the heap and return its value.
+    pub fn remove_by_id(&mut self, id: &Id) -> Option<V> {
+        let handle = self.handles.remove(id)?;
+        self.inner.remove_entry(handle).map(|(_, (_, value))| value)
+    }
+
+    /// Have a look at the id and value of the top-most element of the heap.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn peek(&self) -> Option<(&Id, &V)> {
+        self.inner.peek().map(|(id, value)| (id, value))
+    }
+
+    /// Remove the top-most element from the heap and return its id and value.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn pop(&mut self) -> Option<(Id, V)> {
+        let (id, value) = self.inner.pop()?;
+        self.handles.remove(&id);
+        Some((id, value))
+    }
+
+    /// Remove all elements from the heap.
+    pub fn clear(&mut self) {
+        self.inner.clear();
+        self.handles.clear();
+    }
+}
+
+impl<Id: Hash + Eq + Clone, T: PartialOrd + Copy> AddressableHollowHeap<Id, T, T> {
+    /// Create a new max heap. (`compare = |lhs, rhs| lhs > rhs`)
+    pub fn max_heap() -> AddressableHollowHeap<Id, T, T> {
+        AddressableHollowHeap::new(max_heap_compare, |value| *value)
+    }
+
+    /// Create a new min heap. (`compare = |lhs, rhs| lhs < rhs`)
+    pub fn min_heap() -> AddressableHollowHeap<Id, T, T> {
+        AddressableHollowHeap::new(min_heap_compare, |value| *value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AddressableHollowHeap, Outcome};
+    use crate::UpdateError;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    struct JobId(u32);
+
+    #[test]
+    fn push_or_improve_only_keeps_better_values() {
+        let mut heap: AddressableHollowHeap<JobId, u32, u32> = AddressableHollowHeap::max_heap();
+        assert!(heap.push_or_improve(JobId(1), 10) == Outcome::Inserted);
+        assert!(heap.push_or_improve(JobId(2), 20) == Outcome::Inserted);
+        assert!(heap.push_or_improve(JobId(1), 5) == Outcome::Unchanged);
+        assert!(heap.push_or_improve(JobId(1), 10) == Outcome::Unchanged);
+        assert!(heap.push_or_improve(JobId(1), 30) == Outcome::Improved);
+        assert!(heap.push_or_improve(JobId(2), 25) == Outcome::Improved);
+        assert!(heap.len() == 2);
+        assert!(heap.pop() == Some((JobId(1), 30)));
+        assert!(heap.pop() == Some((JobId(2), 25)));
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn elements_are_addressed_by_id() {
+        let mut heap: AddressableHollowHeap<JobId, u32, u32> = AddressableHollowHeap::min_heap();
+        for id in 0..10 {
+            assert!(heap.push(JobId(id), 100 + id).is_ok());
+        }
+        assert!(heap.push(JobId(3), 0) == Err(0));
+        assert!(heap.len() == 10);
+        assert!(heap.change_key_by_id(&JobId(7), 50) == Ok(()));
+        assert!(heap.change_key_by_id(&JobId(7), 40) == Ok(()));
+        assert!(heap.change_key_by_id(&JobId(7), 60) == Err(UpdateError::NotBetter));
+        assert!(heap.change_item_by_id(&JobId(5), 1) == Ok(()));
+        assert!(heap.change_key_by_id(&JobId(42), 1) == Err(UpdateError::UnknownId));
+        assert!(heap.get_by_id(&JobId(5)) == Some(&1));
+        assert!(heap.remove_by_id(&JobId(2)) == Some(102));
+        assert!(heap.remove_by_id(&JobId(2)).is_none());
+        assert!(!heap.contains_id(&JobId(2)));
+        assert!(heap.peek() == Some((&JobId(5), &1)));
+        assert!(heap.pop() == Some((JobId(5), 1)));
+        assert!(heap.pop() == Some((JobId(7), 107)));
+        assert!(!heap.contains_id(&JobId(7)));
+        assert!(heap.push(JobId(7), 1).is_ok());
+        assert!(heap.pop() == Some((JobId(7), 1)));
+        assert!(heap.pop() == Some((JobId(0), 100)));
+        assert!(heap.len() == 6);
+    }
+}
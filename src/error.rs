@@ -0,0 +1,51 @@
+//! Errors reported by the heaps.
+
+use core::fmt;
+
+/// The reason why the key or item of an element could not be changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UpdateError {
+    /// The heap is empty.
+    EmptyHeap,
+    /// The handle does not refer to an element of the heap, e.g. because it was popped.
+    InvalidHandle,
+    /// The key of the element was already changed, which makes the handle outdated. Use the
+    /// handle returned by the previous change instead.
+    Hollow,
+    /// The new key is not 'better' than the old one according to the compare function.
+    NotBetter,
+    /// The same handle occurs more than once in a batch, see `HollowHeap::change_keys`.
+    DuplicateHandle,
+    /// The new key is rejected by the heap, see `HollowHeapBuilder::reject_nan`.
+    RejectedKey,
+    /// The new key is below the offset of the heap, which has to stay a lower bound of the keys,
+    /// see `HollowHeap::add_to_all_keys`.
+    BelowOffset,
+    /// The heap has no free slot for the changed element, see `FixedHollowHeap::compact`.
+    Full,
+    /// No element with the given id or index is in the heap, see `AddressableHollowHeap` and
+    /// `IndexedHollowHeap`.
+    UnknownId,
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            UpdateError::EmptyHeap => "Should not be trying to change key on empty heap.",
+            UpdateError::InvalidHandle => {
+                "Should not be accessing the heap with an invalid handle."
+            }
+            UpdateError::Hollow => "Should not be changing the key of an item twice.",
+            UpdateError::NotBetter => "Should only change the key to a 'better' value.",
+            UpdateError::DuplicateHandle => "Should only change every element once per batch.",
+            UpdateError::RejectedKey => "Should not be changing to a rejected key.",
+            UpdateError::BelowOffset => "Should not be changing to a key below the offset.",
+            UpdateError::Full => "Should only be changing keys while there is a free slot.",
+            UpdateError::UnknownId => "Should only be changing elements that are in the heap.",
+        };
+        f.write_str(message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UpdateError {}
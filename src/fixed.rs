@@ -0,0 +1,593 @@
+//! A hollow heap with a capacity fixed at compile time that never allocates.
+
+use core::cmp;
+use core::fmt;
+
+use core::num::NonZeroU32;
+
+use crate::slab::{next_generation, Slot};
+use crate::{max_heap_compare, min_heap_compare, Handle, UpdateError, MAX_RANK};
+
+/// A node in a [`FixedHollowHeap`](./struct.FixedHollowHeap.html).
+///
+/// Keeps the item next to its key and links, like the nodes of a
+/// [`HollowHeap`](./struct.HollowHeap.html) built with the `interleaved_layout` feature, and links
+/// to other nodes by slot number.
+#[derive(Debug, Clone)]
+struct FixedNode<K, V> {
+    item: Option<V>,
+    child: Option<Slot>,
+    next: Option<Slot>,
+    second_parent: Option<Slot>,
+    key: K,
+    rank: u8,
+}
+
+impl<K, V> FixedNode<K, V> {
+    fn is_hollow(&self) -> bool {
+        self.item.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Entry<K, V> {
+    Occupied {
+        generation: NonZeroU32,
+        node: FixedNode<K, V>,
+    },
+    Free {
+        generation: NonZeroU32,
+        next_free: Option<Slot>,
+    },
+}
+
+/// A hollow heap that stores up to `N` nodes inline and never allocates.
+///
+/// Apart from `push` returning the value back when the heap is full, it behaves like the
+/// [`HollowHeap`](./struct.HollowHeap.html). Note that hollow nodes (left behind by `change_key`
+/// and `delete`) occupy a slot until they are cleaned up by popping the root or by `compact`.
+///
+/// `change_key` and `change_item` never panic but return an
+/// [`UpdateError`](./enum.UpdateError.html) like `HollowHeap::try_change_key` does, so that stale
+/// handles can be handled without unwinding.
+///
+/// ```rust
+/// use hollow_heap::FixedHollowHeap;
+///
+/// let mut heap: FixedHollowHeap<u8, u8, 2> = FixedHollowHeap::min_heap();
+/// assert!(heap.push(3).is_ok());
+/// assert!(heap.push(1).is_ok());
+/// assert_eq!(heap.push(2), Err(2));
+/// assert_eq!(heap.pop(), Some(1));
+/// ```
+#[derive(Clone)]
+pub struct FixedHollowHeap<K, V, const N: usize> {
+    entries: [Entry<K, V>; N],
+    free_head: Option<Slot>,
+    /// The number of occupied slots, including hollow nodes.
+    nodes: usize,
+    /// The number of hollow nodes.
+    hollow: usize,
+    dag_root: Option<Slot>,
+    compare: fn(&K, &K) -> bool,
+    derive_key: fn(&V) -> K,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, const N: usize> fmt::Debug for FixedHollowHeap<K, V, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "FixedHollowHeap {{ dag_root: {:?}, entries: {:?} }}",
+            self.dag_root, self.entries
+        )
+    }
+}
+
+impl<K: PartialOrd, V, const N: usize> FixedHollowHeap<K, V, N> {
+    pub fn new(compare: fn(&K, &K) -> bool, derive_key: fn(&V) -> K) -> FixedHollowHeap<K, V, N> {
+        FixedHollowHeap {
+            entries: core::array::from_fn(|position| Entry::Free {
+                generation: NonZeroU32::MIN,
+                next_free: if position + 1 < N {
+                    Some(Slot::new(position + 1))
+                } else {
+                    None
+                },
+            }),
+            free_head: if N > 0 { Some(Slot::new(0)) } else { None },
+            nodes: 0,
+            hollow: 0,
+            dag_root: None,
+            compare,
+            derive_key,
+        }
+    }
+
+    /// Test whether there are any elements in the heap.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of elements in the heap, not counting hollow nodes.
+    pub fn len(&self) -> usize {
+        self.nodes - self.hollow
+    }
+
+    /// Test whether all `N` slots are in use, in which case `push` will fail. Slots used by hollow
+    /// nodes can be freed with `compact`.
+    pub fn is_full(&self) -> bool {
+        self.free_head.is_none()
+    }
+
+    /// The number of nodes the heap can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The function deciding whether a key is 'better' than another one.
+    pub fn compare(&self) -> fn(&K, &K) -> bool {
+        self.compare
+    }
+
+    /// The function deriving the key of a pushed value.
+    pub fn derive_key(&self) -> fn(&V) -> K {
+        self.derive_key
+    }
+
+    /// Push a value into the heap.
+    ///
+    /// Returns the handle of the pushed element or the value if the heap is full.
+    pub fn push(&mut self, value: V) -> Result<Handle, V> {
+        let key = (self.derive_key)(&value);
+        self.push_with_key(value, key)
+    }
+
+    /// Push a value into the heap with the provided key.
+    ///
+    /// Circumvents the `derive_key` function and is thus not recommended.
+    pub fn push_with_key(&mut self, value: V, key: K) -> Result<Handle, V> {
+        let slot = match self.free_head {
+            Some(slot) => slot,
+            None => return Err(value),
+        };
+        let handle = self.insert(slot, value, key);
+        self.dag_root = Some(match self.dag_root {
+            Some(root) => self.link(root, slot),
+            None => slot,
+        });
+        Ok(handle)
+    }
+
+    /// Increase or decrease the key (used for sorting) of the Node at `handle`.
+    ///
+    /// **Note:** This function only changes the key, not the item.
+    ///
+    /// Never panics: returns the error together with `new_key` if the heap is empty, `handle` is
+    /// stale or hollow, `new_key` is not greater (or smaller) than the old key (depending on the
+    /// type of heap), or the heap has no free slot for the changed element (see `compact`). The
+    /// heap is unchanged if an error is returned.
+    pub fn change_key(&mut self, handle: Handle, new_key: K) -> Result<Handle, (UpdateError, K)> {
+        self.update(handle, &mut None, new_key)
+    }
+
+    /// Change the item (and recalculate the key) of the Node at `handle`.
+    ///
+    /// Never panics: returns the error together with `new_item` in the same cases as
+    /// `change_key`. The heap is unchanged if an error is returned.
+    pub fn change_item(&mut self, handle: Handle, new_item: V) -> Result<Handle, (UpdateError, V)> {
+        let new_key = (self.derive_key)(&new_item);
+        let mut new_item = Some(new_item);
+        self.update(handle, &mut new_item, new_key)
+            .map_err(|(error, _)| {
+                let item = new_item.take().expect("Item is only consumed on success.");
+                (error, item)
+            })
+    }
+
+    /// Takes the item out of `new_item` only if the update succeeds.
+    fn update(
+        &mut self,
+        handle: Handle,
+        new_item: &mut Option<V>,
+        new_key: K,
+    ) -> Result<Handle, (UpdateError, K)> {
+        if self.dag_root.is_none() {
+            return Err((UpdateError::EmptyHeap, new_key));
+        }
+        let slot = match self.slot_of(handle) {
+            Some(slot) => slot,
+            None => return Err((UpdateError::InvalidHandle, new_key)),
+        };
+        let compare = self.compare;
+        let is_root = self.dag_root == Some(slot);
+        let node = self.node_mut(slot);
+        if node.item.is_none() {
+            return Err((UpdateError::Hollow, new_key));
+        }
+        if !compare(&new_key, &node.key) {
+            return Err((UpdateError::NotBetter, new_key));
+        }
+        if is_root {
+            // the changed value is the root so will be updated in-place
+            if let Some(item) = new_item.take() {
+                node.item = Some(item);
+            }
+            node.key = new_key;
+            return Ok(handle);
+        }
+        // the changed value is not the root and thus will become hollow, which needs a free slot
+        let new_slot = match self.free_head {
+            Some(new_slot) => new_slot,
+            None => return Err((UpdateError::Full, new_key)),
+        };
+        let node = self.node_mut(slot);
+        let old_item = node.item.take().expect("Checked above.");
+        let item = new_item.take().unwrap_or(old_item);
+        let rank = node.rank;
+        self.hollow += 1;
+
+        let new_handle = self.insert(new_slot, item, new_key);
+        let root = self.dag_root.expect("Checked above.");
+        let new_root = self.link(root, new_slot);
+        self.dag_root = Some(new_root);
+        self.node_mut(new_slot).rank = if rank > 1 { rank - 2 } else { 0 };
+        if new_root != new_slot {
+            self.node_mut(new_slot).child = Some(slot);
+            self.node_mut(slot).second_parent = Some(new_slot);
+        }
+        Ok(new_handle)
+    }
+
+    /// Have a look at the top-most value of the heap.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn peek(&self) -> Option<&V> {
+        self.dag_root.and_then(|root| self.node(root).item.as_ref())
+    }
+
+    /// Remove the value at `handle` from the heap.
+    ///
+    /// Returns the new root handle if successful and `None` if deletion failed or the heap is empty
+    /// after the operation.
+    pub fn delete(&mut self, handle: Handle) -> Option<Handle> {
+        let slot = self.slot_of(handle)?;
+        if self.dag_root != Some(slot) {
+            if self.node_mut(slot).item.take().is_some() {
+                self.hollow += 1;
+            }
+            return self.dag_root.map(|root| self.handle_of(root));
+        }
+        // slot is the root from here
+        let mut roots_by_rank: [Option<Slot>; MAX_RANK] = [None; MAX_RANK];
+        let mut max_rank = 0;
+        {
+            let root = self.node_mut(slot);
+            root.next = None;
+            root.second_parent = None;
+        }
+        // Hollow roots are threaded through their `next` links, so no queue is needed.
+        let mut hollow_roots = Some(slot);
+        while let Some(to_delete) = hollow_roots {
+            hollow_roots = self.node(to_delete).next;
+            let mut next_child = self.node(to_delete).child;
+            while let Some(child) = next_child {
+                next_child = self.node(child).next;
+                let current_child = self.node_mut(child);
+                if current_child.is_hollow() {
+                    match current_child.second_parent {
+                        None => {
+                            current_child.next = hollow_roots;
+                            hollow_roots = Some(child);
+                        }
+                        Some(second_parent) => {
+                            if second_parent == to_delete {
+                                next_child = None;
+                            } else {
+                                current_child.next = None;
+                            }
+                            current_child.second_parent = None;
+                        }
+                    }
+                } else {
+                    let mut current = child;
+                    let mut rank = current_child.rank as usize;
+                    while let Some(other) = roots_by_rank[rank].take() {
+                        current = self.ranked_link(other, current);
+                        rank += 1;
+                    }
+                    max_rank = cmp::max(rank, max_rank);
+                    roots_by_rank[rank] = Some(current);
+                }
+            }
+            self.remove(to_delete);
+        }
+        let mut next_root = None;
+        for &root in roots_by_rank[..=max_rank].iter().flatten() {
+            next_root = Some(match next_root {
+                Some(next_root) => self.link(next_root, root),
+                None => root,
+            });
+        }
+        self.dag_root = next_root;
+        // return the handle of the next root
+        next_root.map(|root| self.handle_of(root))
+    }
+
+    /// Remove the top-most value from the heap and return it.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn pop(&mut self) -> Option<V> {
+        let root = self.dag_root?;
+        let item = self.node_mut(root).item.take();
+        self.hollow += 1;
+        let handle = self.handle_of(root);
+        self.delete(handle);
+        item
+    }
+
+    /// Remove all values from the heap.
+    pub fn clear(&mut self) {
+        for position in 0..N {
+            self.remove(Slot::new(position));
+        }
+        self.dag_root = None;
+    }
+
+    /// Free the slots of all hollow nodes and relink the remaining elements into a single tree.
+    ///
+    /// Takes O(N) time and does not allocate. Handles to elements stay valid.
+    pub fn compact(&mut self) {
+        if self.hollow == 0 {
+            return;
+        }
+        self.dag_root = None;
+        for position in 0..N {
+            let slot = Slot::new(position);
+            let node = match &mut self.entries[position] {
+                Entry::Occupied { node, .. } => node,
+                Entry::Free { .. } => continue,
+            };
+            if node.is_hollow() {
+                self.remove(slot);
+                continue;
+            }
+            node.child = None;
+            node.next = None;
+            node.second_parent = None;
+            node.rank = 0;
+            self.dag_root = Some(match self.dag_root {
+                Some(root) => self.link(root, slot),
+                None => slot,
+            });
+        }
+    }
+
+    fn insert(&mut self, slot: Slot, item: V, key: K) -> Handle {
+        let (generation, next_free) = match self.entries[slot.position()] {
+            Entry::Free {
+                generation,
+                next_free,
+            } => (generation, next_free),
+            Entry::Occupied { .. } => unreachable!("The free list only contains free slots."),
+        };
+        self.free_head = next_free;
+        self.nodes += 1;
+        self.entries[slot.position()] = Entry::Occupied {
+            generation,
+            node: FixedNode {
+                item: Some(item),
+                child: None,
+                next: None,
+                second_parent: None,
+                key,
+                rank: 0,
+            },
+        };
+        Handle::new(slot, generation)
+    }
+
+    fn remove(&mut self, slot: Slot) {
+        if let Entry::Occupied { generation, node } = &self.entries[slot.position()] {
+            if node.is_hollow() {
+                self.hollow -= 1;
+            }
+            let generation = *generation;
+            self.entries[slot.position()] = Entry::Free {
+                generation: next_generation(generation),
+                next_free: self.free_head,
+            };
+            self.free_head = Some(slot);
+            self.nodes -= 1;
+        }
+    }
+
+    fn add_child(&mut self, parent: Slot, child: Slot) -> Slot {
+        self.node_mut(child).next = self.node(parent).child;
+        self.node_mut(parent).child = Some(child);
+        parent
+    }
+
+    fn link(&mut self, lhs: Slot, rhs: Slot) -> Slot {
+        if (self.compare)(&self.node(lhs).key, &self.node(rhs).key) {
+            self.add_child(lhs, rhs)
+        } else {
+            self.add_child(rhs, lhs)
+        }
+    }
+
+    fn ranked_link(&mut self, lhs: Slot, rhs: Slot) -> Slot {
+        assert!(self.node(lhs).rank == self.node(rhs).rank);
+        let parent = self.link(lhs, rhs);
+        self.node_mut(parent).rank += 1;
+        parent
+    }
+
+    fn slot_of(&self, handle: Handle) -> Option<Slot> {
+        match self.entries.get(handle.slot().position()) {
+            Some(Entry::Occupied { generation, .. }) if *generation == handle.generation() => {
+                Some(handle.slot())
+            }
+            _ => None,
+        }
+    }
+
+    fn handle_of(&self, slot: Slot) -> Handle {
+        match self.entries[slot.position()] {
+            Entry::Occupied { generation, .. } => Handle::new(slot, generation),
+            Entry::Free { .. } => unreachable!("Only occupied slots are linked."),
+        }
+    }
+
+    fn node(&self, slot: Slot) -> &FixedNode<K, V> {
+        match &self.entries[slot.position()] {
+            Entry::Occupied { node, .. } => node,
+            Entry::Free { .. } => unreachable!("Only occupied slots are linked."),
+        }
+    }
+
+    fn node_mut(&mut self, slot: Slot) -> &mut FixedNode<K, V> {
+        match &mut self.entries[slot.position()] {
+            Entry::Occupied { node, .. } => node,
+            Entry::Free { .. } => unreachable!("Only occupied slots are linked."),
+        }
+    }
+}
+
+impl<T: PartialOrd + Copy, const N: usize> FixedHollowHeap<T, T, N> {
+    /// Create a new empty heap with the chosen compare function.
+    pub fn with_compare(compare: fn(&T, &T) -> bool) -> FixedHollowHeap<T, T, N> {
+        FixedHollowHeap::new(compare, |value| *value)
+    }
+
+    /// Create a new max heap. (`compare = |lhs, rhs| lhs > rhs`)
+    pub fn max_heap() -> FixedHollowHeap<T, T, N> {
+        FixedHollowHeap::with_compare(max_heap_compare)
+    }
+
+    /// Create a new min heap. (`compare = |lhs, rhs| lhs < rhs`)
+    pub fn min_heap() -> FixedHollowHeap<T, T, N> {
+        FixedHollowHeap::with_compare(min_heap_compare)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixedHollowHeap;
+    use crate::UpdateError;
+
+    #[test]
+    fn pop_node_min_heap() {
+        let mut heap: FixedHollowHeap<u8, u8, 8> = FixedHollowHeap::min_heap();
+        assert!(heap.is_empty());
+        for value in [2, 8, 4, 9, 1].iter() {
+            assert!(heap.push(*value).is_ok());
+        }
+        assert!(heap.pop() == Some(1));
+        assert!(heap.pop() == Some(2));
+        assert!(heap.pop() == Some(4));
+        assert!(heap.pop() == Some(8));
+        assert!(heap.pop() == Some(9));
+        assert!(heap.pop().is_none());
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn push_into_full_heap_returns_value() {
+        let mut heap: FixedHollowHeap<u8, u8, 3> = FixedHollowHeap::max_heap();
+        assert!(heap.push(1).is_ok());
+        assert!(heap.push(2).is_ok());
+        assert!(heap.push(3).is_ok());
+        assert!(heap.is_full());
+        assert!(heap.push(4) == Err(4));
+        assert!(heap.compare()(&4, &3));
+        assert!(heap.derive_key()(&4) == 4);
+        assert!(heap.pop() == Some(3));
+        assert!(heap.push(4).is_ok());
+        assert!(heap.pop() == Some(4));
+    }
+
+    #[test]
+    fn change_key_and_delete() {
+        let mut heap: FixedHollowHeap<u16, u16, 8> = FixedHollowHeap::min_heap();
+        heap.push(5).unwrap();
+        let index = heap.push(42).unwrap();
+        let deleted = heap.push(3).unwrap();
+        heap.push(4).unwrap();
+        assert!(heap.change_key(index, 2).is_ok());
+        heap.delete(deleted);
+        assert!(heap.pop() == Some(42));
+        assert!(heap.pop() == Some(4));
+        assert!(heap.pop() == Some(5));
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn change_key_without_free_slot_fails() {
+        let mut heap: FixedHollowHeap<u16, u16, 2> = FixedHollowHeap::min_heap();
+        heap.push(1).unwrap();
+        let index = heap.push(7).unwrap();
+        assert!(heap.change_key(index, 3) == Err((UpdateError::Full, 3)));
+        assert!(heap.change_item(index, 3) == Err((UpdateError::Full, 3)));
+        assert!(heap.pop() == Some(1));
+        assert!(heap.pop() == Some(7));
+    }
+
+    #[test]
+    fn is_empty_ignores_hollow_nodes() {
+        let mut heap: FixedHollowHeap<u16, u16, 8> = FixedHollowHeap::min_heap();
+        heap.push(1).unwrap();
+        heap.push(2).unwrap();
+        let index = heap.push(9).unwrap();
+        heap.pop();
+        heap.change_key(index, 5).unwrap();
+        heap.pop();
+        heap.pop();
+        assert!(heap.is_empty());
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn compact_frees_the_slots_of_hollow_nodes() {
+        let mut heap: FixedHollowHeap<u16, u16, 8> = FixedHollowHeap::min_heap();
+        heap.push(1).unwrap();
+        let mut index = heap.push(100).unwrap();
+        for key in (94..100).rev() {
+            index = heap.change_key(index, key).unwrap();
+        }
+        assert!(heap.is_full());
+        assert!(heap.len() == 2);
+        assert!(heap.change_key(index, 50) == Err((UpdateError::Full, 50)));
+        heap.compact();
+        assert!(!heap.is_full());
+        assert!(heap.len() == 2);
+        let index = heap.change_key(index, 50).unwrap();
+        heap.delete(index);
+        assert!(heap.len() == 1);
+        assert!(heap.pop() == Some(1));
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn change_key_reports_errors_instead_of_panicking() {
+        let mut heap: FixedHollowHeap<u16, u16, 4> = FixedHollowHeap::min_heap();
+        let index = heap.push(3).unwrap();
+        assert!(heap.change_key(index, 5) == Err((UpdateError::NotBetter, 5)));
+        let other = heap.push(7).unwrap();
+        let changed = heap.change_key(other, 4).unwrap();
+        assert!(heap.change_item(other, 2) == Err((UpdateError::Hollow, 2)));
+        assert!(heap.pop() == Some(3));
+        assert!(heap.change_key(index, 1) == Err((UpdateError::InvalidHandle, 1)));
+        assert!(heap.pop() == Some(7));
+        assert!(heap.change_key(changed, 1) == Err((UpdateError::EmptyHeap, 1)));
+    }
+
+    #[test]
+    fn stale_index_is_rejected() {
+        let mut heap: FixedHollowHeap<u8, u8, 2> = FixedHollowHeap::min_heap();
+        let index = heap.push(1).unwrap();
+        assert!(heap.pop() == Some(1));
+        heap.push(2).unwrap();
+        assert!(heap.delete(index).is_none());
+        assert!(heap.pop() == Some(2));
+    }
+}
@@ -0,0 +1,130 @@
+//! Compare functions that give floating point keys a total order, so `NaN` can not break the
+//! order of the heap.
+
+use core::cmp::Ordering;
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
+/// The floating point types (`f32` and `f64`) usable with the `NaN`-aware compare functions.
+///
+/// This trait is sealed and can not be implemented outside of this crate.
+pub trait Float: sealed::Sealed + PartialOrd + Copy {
+    /// Whether the value is `NaN`, see `f64::is_nan`.
+    fn is_nan(self) -> bool;
+
+    /// The IEEE 754 `totalOrder` predicate, see `f64::total_cmp`.
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+impl Float for f32 {
+    fn is_nan(self) -> bool {
+        f32::is_nan(self)
+    }
+
+    fn total_cmp(&self, other: &f32) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+}
+
+impl Float for f64 {
+    fn is_nan(self) -> bool {
+        f64::is_nan(self)
+    }
+
+    fn total_cmp(&self, other: &f64) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+}
+
+/// The comparison function for a min heap that pops `NaN` keys after all other keys.
+pub fn min_heap_compare_nan_last<F: Float>(lhs: &F, rhs: &F) -> bool {
+    !lhs.is_nan() && (rhs.is_nan() || lhs < rhs)
+}
+
+/// The comparison function for a min heap that pops `NaN` keys before all other keys.
+pub fn min_heap_compare_nan_first<F: Float>(lhs: &F, rhs: &F) -> bool {
+    !rhs.is_nan() && (lhs.is_nan() || lhs < rhs)
+}
+
+/// The comparison function for a max heap that pops `NaN` keys after all other keys.
+pub fn max_heap_compare_nan_last<F: Float>(lhs: &F, rhs: &F) -> bool {
+    !lhs.is_nan() && (rhs.is_nan() || lhs > rhs)
+}
+
+/// The comparison function for a max heap that pops `NaN` keys before all other keys.
+pub fn max_heap_compare_nan_first<F: Float>(lhs: &F, rhs: &F) -> bool {
+    !rhs.is_nan() && (lhs.is_nan() || lhs > rhs)
+}
+
+/// The comparison function for a min heap ordered by the IEEE 754 `totalOrder` predicate.
+///
+/// Negative `NaN`s are popped first and positive `NaN`s last. Unlike `<`, this also orders `-0.0`
+/// before `+0.0`.
+pub fn min_heap_total_cmp<F: Float>(lhs: &F, rhs: &F) -> bool {
+    lhs.total_cmp(rhs) == Ordering::Less
+}
+
+/// The comparison function for a max heap ordered by the IEEE 754 `totalOrder` predicate.
+///
+/// Positive `NaN`s are popped first and negative `NaN`s last. Unlike `>`, this also orders `+0.0`
+/// before `-0.0`.
+pub fn max_heap_total_cmp<F: Float>(lhs: &F, rhs: &F) -> bool {
+    lhs.total_cmp(rhs) == Ordering::Greater
+}
+
+/// Used by `HollowHeapBuilder::reject_nan` to validate keys.
+pub(crate) fn is_not_nan<F: Float>(key: &F) -> bool {
+    !key.is_nan()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HollowHeap;
+    use alloc::vec::Vec;
+
+    fn pop_all(compare: fn(&f64, &f64) -> bool) -> Vec<f64> {
+        let mut heap = HollowHeap::with_compare(compare);
+        for key in [2.0, f64::NAN, -1.0, f64::NAN, 0.5, f64::INFINITY].iter() {
+            heap.push(*key);
+        }
+        heap.into_iter().collect()
+    }
+
+    fn assert_pops(compare: fn(&f64, &f64) -> bool, expected: &[f64]) {
+        let popped = pop_all(compare);
+        assert!(popped.len() == expected.len());
+        for (popped, expected) in popped.iter().zip(expected) {
+            assert!(popped == expected || (popped.is_nan() && expected.is_nan()));
+        }
+    }
+
+    #[test]
+    fn nan_is_popped_first_or_last() {
+        let nan = f64::NAN;
+        let inf = f64::INFINITY;
+        assert_pops(min_heap_compare_nan_last, &[-1.0, 0.5, 2.0, inf, nan, nan]);
+        assert_pops(min_heap_compare_nan_first, &[nan, nan, -1.0, 0.5, 2.0, inf]);
+        assert_pops(max_heap_compare_nan_last, &[inf, 2.0, 0.5, -1.0, nan, nan]);
+        assert_pops(max_heap_compare_nan_first, &[nan, nan, inf, 2.0, 0.5, -1.0]);
+    }
+
+    #[test]
+    fn total_cmp_orders_signed_nan_and_zero() {
+        let mut heap = HollowHeap::with_compare(min_heap_total_cmp);
+        for key in [0.0f32, -f32::NAN, 1.0, -0.0, f32::NAN].iter() {
+            heap.push(*key);
+        }
+        let popped: Vec<u32> = heap.into_iter().map(f32::to_bits).collect();
+        let expected: Vec<u32> = [-f32::NAN, -0.0, 0.0, 1.0, f32::NAN]
+            .iter()
+            .map(|key| key.to_bits())
+            .collect();
+        assert!(popped == expected);
+    }
+}
@@ -0,0 +1,613 @@
+//! Many hollow heaps sharing one node storage, so that melding them only links their roots.
+
+use alloc::vec::Vec;
+use core::{cmp, mem, ops};
+
+use crate::slab::{Slab, Slot};
+use crate::store::Dag;
+use crate::{
+    max_heap_compare, min_heap_compare, Handle, HollowHeap, Offset, DEFAULT_REBUILD_RATIO,
+};
+
+/// Identifies a heap in a [`HeapForest`](./struct.HeapForest.html).
+///
+/// Becomes invalid once the heap is melded into another one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HeapId(Handle);
+
+/// A collection of hollow heaps whose nodes live in one shared storage.
+///
+/// Melding two heaps of the same forest takes O(1) time, and handles to elements stay valid
+/// across melds. As the elements do not know which heap they belong to, operations on an element
+/// take the id of the heap it is currently in, which is the heap it was melded into if its
+/// original heap was melded. Passing the id of another heap panics.
+///
+/// Every heap has its own key offset, see `add_to_all_keys`, which it keeps when melded: the
+/// offsets of melded heaps are kept relative to each other, and the keys of their elements are
+/// rebased lazily once they are compared.
+///
+/// ```rust
+/// use hollow_heap::HeapForest;
+///
+/// let mut forest: HeapForest<u32, u32> = HeapForest::min_heap();
+/// let first = forest.new_heap();
+/// let second = forest.new_heap();
+/// forest.push(first, 5);
+/// let handle = forest.push(second, 7);
+/// forest.meld(first, second);
+/// forest.change_key(first, handle, 3);
+/// assert_eq!(forest.pop(first), Some(7));
+/// assert_eq!(forest.pop(first), Some(5));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HeapForest<K, V> {
+    /// Owns the nodes, `inner.dag_root` is only set while operating on one of the heaps.
+    inner: HollowHeap<K, V>,
+    /// The root of every heap and its set in `inner.sets`.
+    roots: Slab<(Option<Slot>, usize)>,
+}
+
+/// A union-find of the sets of heaps of a forest that were melded into each other, with the key
+/// offsets of the heaps kept relative to the parent sets.
+///
+/// Keys are stored relative to the offset of the set their node was last settled in, its
+/// origin. Settling a node adds the relative offsets on the path from its origin to the
+/// representative of its heap, see `HollowHeap::settle`.
+#[derive(Clone)]
+pub(crate) struct HeapSets<K> {
+    /// The parent and rank of every set.
+    sets: Vec<(usize, u8)>,
+    /// `None` until keys are first added to, see `leave`.
+    offsets: Option<Offsets<K>>,
+    /// The set every node was last settled in, by slot position.
+    origins: Vec<usize>,
+    /// The representative of the heap being operated on.
+    current: usize,
+}
+
+/// The offset of every set relative to its parent, or of the heap for representatives, together
+/// with the arithmetic of the key type.
+#[derive(Clone)]
+struct Offsets<K> {
+    values: Vec<K>,
+    add: fn(&K, &K) -> K,
+    sub: fn(&K, &K) -> K,
+}
+
+impl<K: PartialOrd, V> HeapForest<K, V> {
+    pub fn new(compare: fn(&K, &K) -> bool, derive_key: fn(&V) -> K) -> HeapForest<K, V> {
+        HeapForest::with_dag(Dag::new(), compare, derive_key)
+    }
+
+    /// Create a new forest that is able to hold `n` elements without further allocation.
+    pub fn with_capacity(
+        compare: fn(&K, &K) -> bool,
+        derive_key: fn(&V) -> K,
+        n: usize,
+    ) -> HeapForest<K, V> {
+        HeapForest::with_dag(Dag::with_capacity(n), compare, derive_key)
+    }
+
+    fn with_dag(
+        dag: Dag<K, V>,
+        compare: fn(&K, &K) -> bool,
+        derive_key: fn(&V) -> K,
+    ) -> HeapForest<K, V> {
+        let mut inner = HollowHeap::with_dag(dag, compare, Some(derive_key));
+        // a rebuild would link the elements of all heaps into one, see `rebuild_if_too_hollow`
+        inner.rebuild_ratio = None;
+        inner.sets = Some(HeapSets {
+            sets: Vec::new(),
+            offsets: None,
+            origins: Vec::new(),
+            current: 0,
+        });
+        HeapForest {
+            inner,
+            roots: Slab::with_capacity(0),
+        }
+    }
+
+    /// Add a new empty heap to the forest and return its id.
+    pub fn new_heap(&mut self) -> HeapId {
+        let set = self.sets().add_set();
+        let slot = self.roots.insert((None, set));
+        HeapId(self.roots.handle(slot))
+    }
+
+    /// Test whether the heap with the given id exists, i.e. was not melded into another heap.
+    pub fn contains_heap(&self, heap: HeapId) -> bool {
+        self.roots.slot(heap.0).is_some()
+    }
+
+    /// Test whether there are any elements in the heap.
+    pub fn is_empty(&self, heap: HeapId) -> bool {
+        self.roots[self.slot(heap)].0.is_none()
+    }
+
+    /// Push a value into the heap.
+    ///
+    /// Returns the handle of the pushed element.
+    pub fn push(&mut self, heap: HeapId, value: V) -> Handle {
+        self.with_heap(heap, |inner| inner.push(value))
+    }
+
+    /// Change the key of the element at `handle`, which has to be in `heap`, to a 'better' one.
+    /// See [`HollowHeap::change_key`](./struct.HollowHeap.html#method.change_key).
+    pub fn change_key(&mut self, heap: HeapId, handle: Handle, new_key: K) -> Handle {
+        self.check_origin(heap, handle);
+        let handle = self.with_heap(heap, |inner| inner.change_key(handle, new_key));
+        self.rebuild_if_too_hollow();
+        handle
+    }
+
+    /// Change the item of the element at `handle`, which has to be in `heap`. See
+    /// [`HollowHeap::change_item`](./struct.HollowHeap.html#method.change_item).
+    pub fn change_item(&mut self, heap: HeapId, handle: Handle, new_item: V) -> Handle {
+        self.check_origin(heap, handle);
+        let handle = self.with_heap(heap, |inner| inner.change_item(handle, new_item));
+        self.rebuild_if_too_hollow();
+        handle
+    }
+
+    /// Have a look at the top-most value of the heap.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn peek(&self, heap: HeapId) -> Option<&V> {
+        self.roots[self.slot(heap)]
+            .0
+            .and_then(|root| self.inner.dag.item(root))
+    }
+
+    /// Have a look at the key of the top-most value of the heap, including the offset of the heap.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn peek_key(&self, heap: HeapId) -> Option<K>
+    where
+        K: Clone,
+    {
+        let (root, set) = self.roots[self.slot(heap)];
+        let key = self.inner.dag.key(root?).clone();
+        let sets = self.inner.sets.as_ref().expect("Forests have sets.");
+        Some(sets.actual_key(set, key))
+    }
+
+    /// Remove the value at `handle`, which has to be in `heap`.
+    ///
+    /// Returns the new root handle of the heap if successful and `None` if deletion failed or
+    /// the heap is empty after the operation.
+    pub fn delete(&mut self, heap: HeapId, handle: Handle) -> Option<Handle> {
+        self.check_origin(heap, handle);
+        let root = self.with_heap(heap, |inner| inner.delete(handle));
+        self.rebuild_if_too_hollow();
+        root.and_then(|_| self.roots[self.slot(heap)].0)
+            .map(|root| self.inner.dag.handle(root))
+    }
+
+    /// Remove the top-most value from the heap and return it.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn pop(&mut self, heap: HeapId) -> Option<V> {
+        self.pop_entry(heap).map(|(_, item)| item)
+    }
+
+    /// Remove the top-most value from the heap and return it together with its key, including
+    /// the offset of the heap.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn pop_entry(&mut self, heap: HeapId) -> Option<(K, V)> {
+        let entry = self.with_heap(heap, HollowHeap::pop_entry);
+        self.rebuild_if_too_hollow();
+        entry
+    }
+
+    /// Move all elements of the heap `other` into `heap` in O(1) time by linking their roots.
+    ///
+    /// The elements keep their keys including the offsets of their heaps, see `add_to_all_keys`.
+    /// Only the offsets of the two heaps are related to each other, and the keys of the elements
+    /// are rebased lazily, which takes amortized O(log h) time per element for the h heaps melded
+    /// into each other.
+    ///
+    /// The id `other` is invalid afterwards, while handles to its elements stay valid and now
+    /// refer to elements of `heap`.
+    pub fn meld(&mut self, heap: HeapId, other: HeapId) {
+        assert!(heap != other, "Should not be melding a heap with itself.");
+        // both ids are checked before the forest is changed
+        let (slot, other_slot) = (self.slot(heap), self.slot(other));
+        let (other_root, other_set) = self.roots.remove(other_slot).expect("Checked by `slot`.");
+        let set = self.roots[slot].1;
+        self.sets().union(set, other_set);
+        // linking settles both roots, and the root of `heap` is settled even without linking
+        self.with_heap(heap, |inner| {
+            if let Some(other_root) = other_root {
+                inner.add_root(other_root);
+            }
+        });
+    }
+
+    /// Drop the hollow nodes of all heaps and relink the elements of every heap into a single
+    /// tree.
+    ///
+    /// Takes O(n + h) time for the n elements and hollow nodes and the h heaps of the forest.
+    /// Handles to elements stay valid. Happens automatically when there are more than twice as
+    /// many hollow nodes as elements and heaps.
+    pub fn compact(&mut self) {
+        if self.inner.dag.hollow_len() == 0 {
+            return;
+        }
+        for position in 0..self.roots.end() {
+            if let Some(slot) = self.roots.occupied(position) {
+                self.with_root(slot, HollowHeap::compact);
+            }
+        }
+    }
+
+    /// Compact the forest once compacting is paid for by the hollow nodes created since the last
+    /// time. Counting the heaps as well keeps the time spent on walking them bounded.
+    fn rebuild_if_too_hollow(&mut self) {
+        let bound = self.inner.dag.full_len() + self.roots.end();
+        if self.inner.dag.hollow_len() as f32 > DEFAULT_REBUILD_RATIO * bound as f32 {
+            self.compact();
+        }
+    }
+
+    fn slot(&self, heap: HeapId) -> Slot {
+        self.roots
+            .slot(heap.0)
+            .expect("Should not be using the id of a melded heap.")
+    }
+
+    /// Run `operation` on the inner heap with its root set to the root of `heap`.
+    fn with_heap<R, F: FnOnce(&mut HollowHeap<K, V>) -> R>(
+        &mut self,
+        heap: HeapId,
+        operation: F,
+    ) -> R {
+        self.with_root(self.slot(heap), operation)
+    }
+
+    /// Run `operation` on the inner heap with its root and offset set to the ones of the heap at
+    /// `slot`.
+    fn with_root<R, F: FnOnce(&mut HollowHeap<K, V>) -> R>(
+        &mut self,
+        slot: Slot,
+        operation: F,
+    ) -> R {
+        let (root, set) = self.roots[slot];
+        self.inner.offset = self.sets().enter(set);
+        self.inner.dag_root = root;
+        let result = operation(&mut self.inner);
+        if let Some(root) = self.inner.dag_root {
+            // `peek_key` expects the root to be settled
+            self.inner.settle(root);
+        }
+        let offset = self.inner.offset.take();
+        self.sets().leave(offset);
+        self.roots[slot].0 = self.inner.dag_root.take();
+        result
+    }
+
+    fn sets(&mut self) -> &mut HeapSets<K> {
+        self.inner.sets.as_mut().expect("Forests have sets.")
+    }
+
+    /// Assert that the node at `handle` is in `heap`, i.e. was pushed into `heap` or into a heap
+    /// melded into it. Stale handles are left to the operation to report.
+    fn check_origin(&mut self, heap: HeapId, handle: Handle) {
+        let slot = match self.inner.dag.slot(handle) {
+            Some(slot) => slot,
+            None => return,
+        };
+        let set = self.roots[self.slot(heap)].1;
+        assert!(
+            self.sets().contains(set, slot),
+            "Should only be passing handles to elements of the given heap."
+        );
+    }
+}
+
+impl<K, V> HeapForest<K, V>
+where
+    K: PartialOrd + Clone + ops::Add<Output = K> + ops::Sub<Output = K>,
+{
+    /// Add `delta` to the keys of all elements in `heap` in O(1) time. See
+    /// [`HollowHeap::add_to_all_keys`](./struct.HollowHeap.html#method.add_to_all_keys), which
+    /// also describes that the offset is a lower bound of the keys pushed or changed to
+    /// afterwards, which `push` and `change_key` assert.
+    ///
+    /// Melding keeps the offsets of both heaps, and the offset of the melded heap is the smaller
+    /// one, which is a lower bound of the keys of both.
+    pub fn add_to_all_keys(&mut self, heap: HeapId, delta: K) {
+        self.with_heap(heap, |inner| inner.add_to_all_keys(delta));
+    }
+}
+
+impl<K: PartialOrd> HeapSets<K> {
+    /// Add a set for a new heap and return it.
+    fn add_set(&mut self) -> usize {
+        let set = self.sets.len();
+        self.sets.push((set, 0));
+        if let Some(offsets) = &mut self.offsets {
+            let zero = (offsets.sub)(&offsets.values[set - 1], &offsets.values[set - 1]);
+            offsets.values.push(zero);
+        }
+        set
+    }
+
+    /// Start operating on the heap of `set`, returning the offset of the heap for the inner heap
+    /// to apply.
+    fn enter(&mut self, set: usize) -> Option<Offset<K>> {
+        self.current = self.find(set);
+        let current = self.current;
+        self.offsets.as_mut().map(|offsets| {
+            let zero = (offsets.sub)(&offsets.values[current], &offsets.values[current]);
+            Offset {
+                value: mem::replace(&mut offsets.values[current], zero),
+                add: offsets.add,
+                sub: offsets.sub,
+            }
+        })
+    }
+
+    /// Stop operating on the current heap, taking back its offset, which is the first offset of
+    /// the forest if keys were added to for the first time.
+    fn leave(&mut self, offset: Option<Offset<K>>) {
+        let offset = match offset {
+            Some(offset) => offset,
+            None => return,
+        };
+        let len = self.sets.len();
+        let offsets = self.offsets.get_or_insert_with(|| Offsets {
+            values: (0..len)
+                .map(|_| (offset.sub)(&offset.value, &offset.value))
+                .collect(),
+            add: offset.add,
+            sub: offset.sub,
+        });
+        offsets.values[self.current] = offset.value;
+    }
+
+    /// Record that the node at `slot` was inserted into the current heap.
+    pub(crate) fn record(&mut self, slot: Slot) {
+        let position = slot.position();
+        if position == self.origins.len() {
+            self.origins.push(self.current);
+        } else {
+            self.origins[position] = self.current;
+        }
+    }
+
+    /// Rebase `key`, the key of the node at `slot`, from the offset of its origin to the offset
+    /// of the current heap.
+    pub(crate) fn settle(&mut self, slot: Slot, key: &mut K) {
+        let position = slot.position();
+        let origin = self.origins[position];
+        if origin == self.current {
+            return;
+        }
+        let representative = self.find(origin);
+        debug_assert!(representative == self.current);
+        if let Some(offsets) = &self.offsets {
+            let mut set = origin;
+            while set != representative {
+                *key = (offsets.add)(key, &offsets.values[set]);
+                set = self.sets[set].0;
+            }
+        }
+        self.origins[position] = representative;
+    }
+
+    /// Whether the node at `slot` belongs to the heap of `set`.
+    fn contains(&mut self, set: usize, slot: Slot) -> bool {
+        let origin = self.origins[slot.position()];
+        self.find(origin) == self.find(set)
+    }
+
+    /// The actual key for the settled `key` of an element of the heap of `set`.
+    fn actual_key(&self, mut set: usize, key: K) -> K {
+        while self.sets[set].0 != set {
+            set = self.sets[set].0;
+        }
+        match &self.offsets {
+            Some(offsets) => (offsets.add)(&key, &offsets.values[set]),
+            None => key,
+        }
+    }
+
+    /// The representative of `set`, halving the path to it and folding the offsets of skipped
+    /// sets into the offsets relative to their new parents.
+    fn find(&mut self, mut set: usize) -> usize {
+        while self.sets[set].0 != set {
+            let parent = self.sets[set].0;
+            let grandparent = self.sets[parent].0;
+            if grandparent != parent {
+                if let Some(offsets) = &mut self.offsets {
+                    offsets.values[set] =
+                        (offsets.add)(&offsets.values[set], &offsets.values[parent]);
+                }
+                self.sets[set].0 = grandparent;
+            }
+            set = grandparent;
+        }
+        set
+    }
+
+    /// Join the sets of two heaps. The set with the smaller offset becomes the representative, so
+    /// that relative offsets are never negative and unsigned keys do not underflow, and the rank
+    /// decides between equal offsets.
+    fn union(&mut self, lhs: usize, rhs: usize) {
+        let (lhs, rhs) = (self.find(lhs), self.find(rhs));
+        let (parent, child) = match &self.offsets {
+            Some(offsets) if offsets.values[lhs] < offsets.values[rhs] => (lhs, rhs),
+            Some(offsets) if offsets.values[rhs] < offsets.values[lhs] => (rhs, lhs),
+            _ if self.sets[lhs].1 < self.sets[rhs].1 => (rhs, lhs),
+            _ => (lhs, rhs),
+        };
+        self.sets[parent].1 = cmp::max(self.sets[parent].1, self.sets[child].1.saturating_add(1));
+        if let Some(offsets) = &mut self.offsets {
+            offsets.values[child] = (offsets.sub)(&offsets.values[child], &offsets.values[parent]);
+        }
+        self.sets[child].0 = parent;
+    }
+}
+
+impl<T: PartialOrd + Copy> HeapForest<T, T> {
+    /// Create a new forest of max heaps. (`compare = |lhs, rhs| lhs > rhs`)
+    pub fn max_heap() -> HeapForest<T, T> {
+        HeapForest::new(max_heap_compare, |value| *value)
+    }
+
+    /// Create a new forest of min heaps. (`compare = |lhs, rhs| lhs < rhs`)
+    pub fn min_heap() -> HeapForest<T, T> {
+        HeapForest::new(min_heap_compare, |value| *value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeapForest;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn heaps_are_independent() {
+        let mut forest: HeapForest<u32, u32> = HeapForest::max_heap();
+        let first = forest.new_heap();
+        let second = forest.new_heap();
+        for value in [3, 9, 1].iter() {
+            forest.push(first, *value);
+        }
+        let handle = forest.push(second, 4);
+        forest.push(second, 6);
+        assert!(forest.peek(first) == Some(&9));
+        forest.change_item(second, handle, 8);
+        assert!(forest.pop(second) == Some(8));
+        assert!(forest.pop(first) == Some(9));
+        assert!(forest.pop(second) == Some(6));
+        assert!(forest.is_empty(second));
+        assert!(!forest.is_empty(first));
+    }
+
+    #[test]
+    fn meld_keeps_handles_valid() {
+        let mut forest: HeapForest<u32, u32> = HeapForest::min_heap();
+        let heaps: Vec<_> = (0..4).map(|_| forest.new_heap()).collect();
+        let mut handles = Vec::new();
+        for (i, heap) in heaps.iter().enumerate() {
+            for value in 0..5 {
+                handles.push(forest.push(*heap, 100 + value * 4 + i as u32));
+            }
+        }
+        forest.pop(heaps[1]);
+        forest.meld(heaps[0], heaps[1]);
+        forest.meld(heaps[2], heaps[3]);
+        forest.meld(heaps[0], heaps[2]);
+        assert!(!forest.contains_heap(heaps[1]));
+        assert!(forest.contains_heap(heaps[0]));
+        forest.change_key(heaps[0], handles[19], 1);
+        forest.delete(heaps[0], handles[0]);
+        let popped: Vec<_> = (0..20).filter_map(|_| forest.pop(heaps[0])).collect();
+        let mut expected: Vec<_> = (0..20u32).map(|i| 100 + (i % 5) * 4 + i / 5).collect();
+        expected.retain(|value| ![100, 101, 119].contains(value));
+        expected.sort();
+        expected.insert(0, 119);
+        assert!(popped == expected);
+    }
+
+    #[test]
+    fn compact_keeps_heaps_apart() {
+        let mut forest: HeapForest<u32, u32> = HeapForest::min_heap();
+        let first = forest.new_heap();
+        let second = forest.new_heap();
+        let mut handles: Vec<_> = (0..50)
+            .map(|value| forest.push(first, 10_000 + value))
+            .collect();
+        let other = forest.push(second, 1);
+        for round in 0..20 {
+            for (i, handle) in handles.iter_mut().enumerate() {
+                *handle = forest.change_key(first, *handle, 9_000 - round * 100 - i as u32);
+            }
+            let nodes = forest.inner.dag.len();
+            assert!(nodes <= 3 * (50 + 1 + forest.roots.end()));
+        }
+        forest.compact();
+        assert!(forest.inner.dag.hollow_len() == 0);
+        forest.change_key(second, other, 0);
+        assert!(forest.pop(second) == Some(1));
+        assert!(forest.is_empty(second));
+        forest.delete(first, handles[49]);
+        let popped: Vec<_> = (0..60).filter_map(|_| forest.pop(first)).collect();
+        assert!(popped.len() == 49);
+        assert!(popped.windows(2).all(|pair| pair[0] > pair[1]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn handles_of_other_heaps_are_rejected() {
+        let mut forest: HeapForest<u32, u32> = HeapForest::min_heap();
+        let first = forest.new_heap();
+        let second = forest.new_heap();
+        let third = forest.new_heap();
+        let root = forest.push(first, 1);
+        forest.push(second, 2);
+        forest.meld(second, third);
+        forest.delete(second, root);
+    }
+
+    #[test]
+    fn meld_keeps_the_offsets_of_both_heaps() {
+        let mut forest: HeapForest<u32, u32> = HeapForest::min_heap();
+        let heaps: Vec<_> = (0..3).map(|_| forest.new_heap()).collect();
+        forest.push(heaps[0], 10);
+        forest.push(heaps[0], 20);
+        forest.add_to_all_keys(heaps[0], 5);
+        let handle = forest.push(heaps[1], 12);
+        forest.push(heaps[1], 30);
+        forest.add_to_all_keys(heaps[1], 100);
+        forest.meld(heaps[1], heaps[0]);
+        assert!(forest.peek_key(heaps[1]) == Some(15));
+        forest.push(heaps[1], 16);
+        forest.push(heaps[2], 1);
+        forest.meld(heaps[2], heaps[1]);
+        forest.add_to_all_keys(heaps[2], 1);
+        forest.change_key(heaps[2], handle, 20);
+        let popped: Vec<_> = (0..6).filter_map(|_| forest.pop_entry(heaps[2])).collect();
+        assert!(popped == [(2, 1), (16, 10), (17, 16), (20, 12), (26, 20), (131, 30)]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn meld_checks_both_ids_first() {
+        let mut forest: HeapForest<u32, u32> = HeapForest::min_heap();
+        let first = forest.new_heap();
+        let second = forest.new_heap();
+        let third = forest.new_heap();
+        forest.push(third, 1);
+        forest.meld(first, second);
+        let melded = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| {
+            forest.meld(second, third);
+        }));
+        assert!(melded.is_err());
+        assert!(forest.contains_heap(third));
+        assert!(forest.pop(third) == Some(1));
+    }
+
+    #[test]
+    fn handles_stay_valid_in_melded_heaps() {
+        let mut forest: HeapForest<u32, u32> = HeapForest::min_heap();
+        let heaps: Vec<_> = (0..6).map(|_| forest.new_heap()).collect();
+        let handles: Vec<_> = heaps
+            .iter()
+            .enumerate()
+            .map(|(i, heap)| forest.push(*heap, 10 + i as u32))
+            .collect();
+        forest.meld(heaps[1], heaps[0]);
+        forest.meld(heaps[2], heaps[3]);
+        forest.meld(heaps[2], heaps[1]);
+        forest.change_key(heaps[2], handles[0], 5);
+        let handle = forest.change_key(heaps[2], handles[3], 4);
+        forest.change_item(heaps[2], handle, 3);
+        assert!(forest.delete(heaps[4], handles[4]).is_none());
+        assert!(forest.pop(heaps[2]) == Some(3));
+        assert!(forest.pop(heaps[2]) == Some(10));
+    }
+}
@@ -0,0 +1,55 @@
+//! The new handles of elements that were moved from one heap into another.
+
+use alloc::vec::Vec;
+
+use crate::Handle;
+
+/// Maps the handles of moved elements in their old heap to their handles in the new heap, see
+/// [`HollowHeap::split_off`](./struct.HollowHeap.html#method.split_off) and
+/// [`HollowHeap::map`](./struct.HollowHeap.html#method.map).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HandleMap {
+    /// Pairs of old and new handle, sorted by the old handle once built.
+    pairs: Vec<(Handle, Handle)>,
+}
+
+impl HandleMap {
+    pub(crate) fn with_capacity(n: usize) -> HandleMap {
+        HandleMap {
+            pairs: Vec::with_capacity(n),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, old: Handle, new: Handle) {
+        self.pairs.push((old, new));
+    }
+
+    /// Sort the pairs so that `get` can search them.
+    pub(crate) fn build(mut self) -> HandleMap {
+        self.pairs.sort_unstable_by_key(|(old, _)| *old);
+        self
+    }
+
+    /// The new handle of the element that had the handle `old`, or `None` if it was not moved.
+    pub fn get(&self, old: Handle) -> Option<Handle> {
+        self.pairs
+            .binary_search_by_key(&old, |(old, _)| *old)
+            .ok()
+            .map(|index| self.pairs[index].1)
+    }
+
+    /// The number of moved elements.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Test whether no element was moved.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Iterate over the pairs of old and new handle, ordered by the old handle.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, Handle)> + '_ {
+        self.pairs.iter().copied()
+    }
+}
@@ -0,0 +1,155 @@
+//! A hollow heap of the indices `0..n` with a key each, e.g. the vertices of a graph.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::store::Dag;
+use crate::{max_heap_compare, min_heap_compare, Handle, HollowHeap, UpdateError};
+
+/// A hollow heap of indices in `0..n`, each at most once, ordered by their keys.
+///
+/// The handle of every index is kept in a table of size `n`, so unlike the
+/// [`AddressableHollowHeap`](./struct.AddressableHollowHeap.html) no hashing is needed. This is
+/// the shape of the priority queue in Dijkstra's or Prim's algorithm.
+///
+/// ```rust
+/// use hollow_heap::IndexedHollowHeap;
+///
+/// let mut distances: IndexedHollowHeap<u32> = IndexedHollowHeap::min_heap(4);
+/// distances.insert(0, 7).unwrap();
+/// distances.insert(3, 5).unwrap();
+/// distances.decrease_key(0, 2).unwrap();
+/// assert_eq!(distances.pop(), Some((0, 2)));
+/// assert!(distances.contains(3));
+/// ```
+#[derive(Clone, Debug)]
+pub struct IndexedHollowHeap<K> {
+    inner: HollowHeap<K, usize>,
+    handles: Vec<Option<Handle>>,
+}
+
+impl<K: PartialOrd> IndexedHollowHeap<K> {
+    /// Create a new empty heap for the indices `0..n` with the chosen compare function.
+    ///
+    /// The heap will be able to hold all `n` indices without further allocation.
+    pub fn new(n: usize, compare: fn(&K, &K) -> bool) -> IndexedHollowHeap<K> {
+        IndexedHollowHeap {
+            inner: HollowHeap::with_dag(Dag::with_capacity(n), compare, None),
+            handles: vec![None; n],
+        }
+    }
+
+    /// Create a new max heap for the indices `0..n`. (`compare = |lhs, rhs| lhs > rhs`)
+    pub fn max_heap(n: usize) -> IndexedHollowHeap<K> {
+        IndexedHollowHeap::new(n, max_heap_compare)
+    }
+
+    /// Create a new min heap for the indices `0..n`. (`compare = |lhs, rhs| lhs < rhs`)
+    pub fn min_heap(n: usize) -> IndexedHollowHeap<K> {
+        IndexedHollowHeap::new(n, min_heap_compare)
+    }
+
+    /// Test whether there are any indices in the heap.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// The number of indices in the heap.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Test whether `index` is in the heap.
+    pub fn contains(&self, index: usize) -> bool {
+        self.handles.get(index).is_some_and(Option::is_some)
+    }
+
+    /// Insert `index` with the given key.
+    ///
+    /// Asserts that `index` is smaller than `n`. Returns the key back if `index` already is in
+    /// the heap.
+    pub fn insert(&mut self, index: usize, key: K) -> Result<(), K> {
+        assert!(
+            index < self.handles.len(),
+            "Should only insert indices smaller than the size of the heap."
+        );
+        if self.handles[index].is_some() {
+            return Err(key);
+        }
+        self.handles[index] = Some(self.inner.push_with_key(index, key));
+        Ok(())
+    }
+
+    /// Change the key of `index` to a 'better' one, i.e. a smaller one for a min heap.
+    pub fn decrease_key(&mut self, index: usize, new_key: K) -> Result<(), UpdateError> {
+        let handle = self
+            .handles
+            .get_mut(index)
+            .and_then(Option::as_mut)
+            .ok_or(UpdateError::UnknownId)?;
+        *handle = self.inner.try_change_key(*handle, new_key)?;
+        Ok(())
+    }
+
+    /// Have a look at the top-most index and its key.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn peek(&self) -> Option<(usize, &K)> {
+        self.inner.peek_entry().map(|(key, index)| (*index, key))
+    }
+
+    /// Remove the top-most index from the heap and return it with its key.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn pop(&mut self) -> Option<(usize, K)> {
+        let (key, index) = self.inner.pop_entry()?;
+        self.handles[index] = None;
+        Some((index, key))
+    }
+
+    /// Remove all indices from the heap.
+    pub fn clear(&mut self) {
+        self.inner.clear();
+        for handle in self.handles.iter_mut() {
+            *handle = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IndexedHollowHeap;
+    use crate::UpdateError;
+
+    #[test]
+    fn indices_are_popped_by_key() {
+        let mut heap: IndexedHollowHeap<u32> = IndexedHollowHeap::min_heap(5);
+        for (index, key) in [40, 10, 30, 20].iter().enumerate() {
+            assert!(heap.insert(index, *key).is_ok());
+        }
+        assert!(heap.insert(1, 0) == Err(0));
+        assert!(heap.len() == 4);
+        assert!(!heap.contains(4));
+        assert!(heap.decrease_key(0, 5) == Ok(()));
+        assert!(heap.decrease_key(0, 1) == Ok(()));
+        assert!(heap.decrease_key(2, 35) == Err(UpdateError::NotBetter));
+        assert!(heap.decrease_key(4, 1) == Err(UpdateError::UnknownId));
+        assert!(heap.decrease_key(9, 1) == Err(UpdateError::UnknownId));
+        assert!(heap.peek() == Some((0, &1)));
+        assert!(heap.pop() == Some((0, 1)));
+        assert!(!heap.contains(0));
+        assert!(heap.insert(0, 15).is_ok());
+        assert!(heap.pop() == Some((1, 10)));
+        assert!(heap.pop() == Some((0, 15)));
+        assert!(heap.pop() == Some((3, 20)));
+        assert!(heap.pop() == Some((2, 30)));
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_out_of_range_panics() {
+        let mut heap: IndexedHollowHeap<u32> = IndexedHollowHeap::min_heap(2);
+        let _ = heap.insert(2, 0);
+    }
+}
@@ -0,0 +1,179 @@
+//! A hollow heap of `(key, value)` pairs whose keys are provided on push instead of derived.
+
+use crate::store::Dag;
+use crate::{max_heap_compare, min_heap_compare, Handle, HollowHeap, UpdateError};
+
+/// A hollow heap that stores each value together with a key provided by the caller.
+///
+/// Useful when the priority of a value is computed elsewhere and can not be derived from the
+/// value itself. Otherwise it behaves like the [`HollowHeap`](./struct.HollowHeap.html).
+///
+/// ```rust
+/// use hollow_heap::KeyedHollowHeap;
+///
+/// let mut heap: KeyedHollowHeap<u32, &str> = KeyedHollowHeap::min_heap();
+/// heap.push(7, "seven");
+/// let handle = heap.push(9, "nine");
+/// heap.change_key(handle, 3);
+/// assert_eq!(heap.pop(), Some((3, "nine")));
+/// assert_eq!(heap.pop(), Some((7, "seven")));
+/// ```
+#[derive(Clone, Debug)]
+pub struct KeyedHollowHeap<K, V> {
+    inner: HollowHeap<K, V>,
+}
+
+impl<K: PartialOrd, V> KeyedHollowHeap<K, V> {
+    /// Create a new empty heap with the chosen compare function.
+    pub fn new(compare: fn(&K, &K) -> bool) -> KeyedHollowHeap<K, V> {
+        KeyedHollowHeap {
+            inner: HollowHeap::with_dag(Dag::new(), compare, None),
+        }
+    }
+
+    /// Create a new min heap with the specified capacity.
+    ///
+    /// The heap will be able to hold `n` elements without further allocation.
+    pub fn with_capacity(n: usize) -> KeyedHollowHeap<K, V> {
+        KeyedHollowHeap::with_compare_and_capacity(min_heap_compare, n)
+    }
+
+    /// Create a new empty heap with the chosen compare function and the specified capacity.
+    pub fn with_compare_and_capacity(
+        compare: fn(&K, &K) -> bool,
+        n: usize,
+    ) -> KeyedHollowHeap<K, V> {
+        KeyedHollowHeap {
+            inner: HollowHeap::with_dag(Dag::with_capacity(n), compare, None),
+        }
+    }
+
+    /// Create a new max heap. (`compare = |lhs, rhs| lhs > rhs`)
+    pub fn max_heap() -> KeyedHollowHeap<K, V> {
+        KeyedHollowHeap::new(max_heap_compare)
+    }
+
+    /// Create a new min heap. (`compare = |lhs, rhs| lhs < rhs`)
+    pub fn min_heap() -> KeyedHollowHeap<K, V> {
+        KeyedHollowHeap::new(min_heap_compare)
+    }
+
+    /// Test whether there are any elements in the heap.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// The number of elements in the heap.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Push a value with the given key into the heap.
+    ///
+    /// Returns the handle of the pushed element.
+    pub fn push(&mut self, key: K, value: V) -> Handle {
+        self.inner.push_with_key(value, key)
+    }
+
+    /// Change the key of the element at `handle` to a 'better' one, see
+    /// [`HollowHeap::change_key`](./struct.HollowHeap.html#method.change_key).
+    pub fn change_key(&mut self, handle: Handle, new_key: K) -> Handle {
+        self.inner.change_key(handle, new_key)
+    }
+
+    /// Like `change_key`, but returns an error instead of panicking.
+    pub fn try_change_key(&mut self, handle: Handle, new_key: K) -> Result<Handle, UpdateError> {
+        self.inner.try_change_key(handle, new_key)
+    }
+
+    /// Have a look at the top-most key and value of the heap.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn peek(&self) -> Option<(&K, &V)> {
+        self.inner.peek_entry()
+    }
+
+    /// Remove the value at `handle` from the heap.
+    ///
+    /// Returns the new root handle if successful and `None` if deletion failed or the heap is
+    /// empty after the operation.
+    pub fn delete(&mut self, handle: Handle) -> Option<Handle> {
+        self.inner.delete(handle)
+    }
+
+    /// Remove the top-most key and value from the heap and return them.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        self.inner.pop_entry()
+    }
+
+    /// Drop all hollow nodes, see [`HollowHeap::compact`](./struct.HollowHeap.html#method.compact).
+    pub fn compact(&mut self) {
+        self.inner.compact()
+    }
+
+    /// Remove all keys and values from the heap.
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+}
+
+impl<K: PartialOrd, V> IntoIterator for KeyedHollowHeap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { inner: self }
+    }
+}
+
+/// Pops the `(key, value)` pairs of a [`KeyedHollowHeap`](./struct.KeyedHollowHeap.html) in order.
+#[derive(Clone, Debug)]
+pub struct IntoIter<K, V> {
+    inner: KeyedHollowHeap<K, V>,
+}
+
+impl<K: PartialOrd, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.inner.inner.dag.full_len();
+        (len, Some(len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyedHollowHeap;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn pops_keys_with_values() {
+        let mut heap: KeyedHollowHeap<f32, char> = KeyedHollowHeap::max_heap();
+        heap.push(0.5, 'a');
+        let b = heap.push(0.25, 'b');
+        heap.push(0.75, 'c');
+        assert!(heap.peek() == Some((&0.75, &'c')));
+        heap.change_key(b, 1.0);
+        assert!(heap.len() == 3);
+        let popped: Vec<_> = heap.into_iter().collect();
+        assert!(popped == [(1.0, 'b'), (0.75, 'c'), (0.5, 'a')]);
+    }
+
+    #[test]
+    fn values_do_not_need_to_derive_keys() {
+        struct Job;
+
+        let mut heap: KeyedHollowHeap<u8, Job> = KeyedHollowHeap::with_capacity(2);
+        let first = heap.push(3, Job);
+        heap.push(2, Job);
+        assert!(heap.try_change_key(first, 4).is_err());
+        heap.delete(first);
+        assert!(heap.pop().map(|(key, _)| key) == Some(2));
+        assert!(heap.is_empty());
+    }
+}
@@ -4,8 +4,37 @@ A heap with great asymptotic run-time based on the
 
 **Note: pre-alpha software unfit for production.**
 
-Extra Note: The hollow heap (at least in this implementation) has high overhead per node because of
-all the book-keeping that needs to be done.
+Extra Note: The hollow heap has some overhead per node because of all the book-keeping that needs
+to be done. Nodes live in a slab and link to each other with 4 byte slot numbers, which brought the
+size of a stored node (including the slab's bookkeeping) down considerably compared to the previous
+`generational_arena` based storage. Keys, ranks and links are stored apart from the items so that
+the consolidation in `pop` and `delete` does not have to pull large items into the cache. The
+`interleaved_layout` feature stores every item next to its key and links in one slab instead.
+
+**Note:** `interleaved_layout` is a global layout switch, not an additive feature. Cargo unifies
+features across the dependency graph, so if any crate enables it, every heap of every crate
+using `hollow_heap` in the same build gets the interleaved layout, with its memory and
+performance characteristics. Only enable it in the final binary, not in libraries.
+
+Bytes per node, including the slab's bookkeeping:
+
+| key / value        | `generational_arena` | `interleaved_layout` | keys apart from items |
+|--------------------|----------------------|----------------------|-----------------------|
+| `f32` / `()`       | 112                  | 32                   | 36                    |
+| `u32` / `u32`      | 120                  | 36                   | 36                    |
+| `u64` / `u64`      | 136                  | 56                   | 56                    |
+| `u64` / `[u8; 64]` | 184                  | 112                  | 104                   |
+
+Timings of `examples/layout_benchmark.rs` (200k pushes, `change_key` on half of them, pop all;
+best of 5, `u64` keys) on a single core machine, measured with
+`cargo run --release --example layout_benchmark` with and without `--features interleaved_layout`:
+
+| item size | `interleaved_layout` | keys apart from items |
+|-----------|----------------------|-----------------------|
+| 8 bytes   | 97 ms                | 99 ms                 |
+| 64 bytes  | 111 ms               | 102 ms                |
+| 256 bytes | 140 ms               | 129 ms                |
+| 1 KiB     | 274 ms               | 253 ms                |
 
 ## Why implement it then?
 
@@ -14,7 +43,31 @@ All heap operations in a hollow heap except `delete` and `pop` take O(1) time.
 
 ## Features
 
-* Zero `unsafe` (by using `generational_arena`)
+* Zero `unsafe`
+* A choice of node layout: keys and links apart from the items (default), or interleaved with
+  the items by enabling the `interleaved_layout` feature, which switches the layout for the
+  whole build
+* `no_std` support (with `alloc`) by disabling the default `std` feature
+* An allocation-free [`FixedHollowHeap`](./struct.FixedHollowHeap.html) with a capacity fixed
+  at compile time
+* Both the two-parent and the one-parent variant from the paper, see
+  [`HollowHeapBuilder::one_parent`](./struct.HollowHeapBuilder.html#method.one_parent)
+* Lazy linking of roots, see
+  [`HollowHeapBuilder::lazy_linking`](./struct.HollowHeapBuilder.html#method.lazy_linking)
+* A [`KeyedHollowHeap`](./struct.KeyedHollowHeap.html) for keys that are not derived from the
+  values
+* An [`AddressableHollowHeap`](./struct.AddressableHollowHeap.html) that addresses elements by
+  id (requires the `std` feature)
+* An [`IndexedHollowHeap`](./struct.IndexedHollowHeap.html) for indices in `0..n`, e.g. graph
+  vertices
+* A [`HeapForest`](./struct.HeapForest.html) of heaps sharing one storage, which melds heaps in
+  O(1) time
+* Adding to all keys in O(1) with
+  [`HollowHeap::add_to_all_keys`](./struct.HollowHeap.html#method.add_to_all_keys), also for
+  the heaps of a `HeapForest`, which keep their offsets when melded
+* `NaN`-safe compare functions for `f32` and `f64` keys like
+  [`min_heap_total_cmp`](./fn.min_heap_total_cmp.html), and
+  [`HollowHeapBuilder::reject_nan`](./struct.HollowHeapBuilder.html#method.reject_nan)
 
 ## Usage
 
@@ -22,7 +75,14 @@ First, add `hollow_heap` to your `Cargo.toml`:
 
 ```toml
 [dependencies]
-hollow_heap = "0.5"
+hollow_heap = "0.6"
+```
+
+For `no_std` environments that provide an allocator, disable the default features instead:
+
+```toml
+[dependencies]
+hollow_heap = { version = "0.6", default-features = false }
 ```
 
 Then, import the crate and use the
@@ -68,84 +128,50 @@ println!("{:?}", heap.pop()); // 42
 println!("{:?}", heap.pop()); // None
 ```
  */
-use std::cmp;
-use std::collections::VecDeque;
-
-use generational_arena::{Arena, Index};
-
-/// A node in a hollow heap.
-///
-/// `item` holds the value.
-/// `key` is used for comparison.
-/// Hollow nodes are represented by setting `item` to `None`.
-#[derive(Debug, Clone)]
-struct Node<I, K, V> {
-    index: Option<I>,
-    item: Option<V>,
-    child: Option<I>,
-    next: Option<I>,
-    second_parent: Option<I>,
-    key: K,
-    /// Quote from the [hollow heap paper](https://arxiv.org/abs/1510.06535):
-    /// > The rank of a node in a [...] hollow heap of `N` nodes is at most `logφN` (logarithm of N
-    /// with the golden ratio)
-    ///
-    /// This means that the rank in a hollow heap whose size is limited by `usize` is never greater
-    /// than `logφ(usize::max_value) = 92.18688578640361` and thus fits in a `u8`.
-    rank: u8,
-}
-
-impl<K: PartialOrd, V> Node<Index, K, V> {
-    /// Note: incomplete because index is not set correctly.
-    fn new(item: V, key: K) -> Node<Index, K, V> {
-        Node {
-            index: None,
-            item: Some(item),
-            child: None,
-            next: None,
-            second_parent: None,
-            key,
-            rank: 0,
-        }
-    }
-
-    pub fn new_in_arena(arena: &mut Arena<Node<Index, K, V>>, item: V, key: K) -> Index {
-        // safe because we assign index later
-        let node = Self::new(item, key);
-        let index = arena.insert(node);
-        arena[index].index = Some(index);
-        index
-    }
-
-    fn add_child(&mut self, new_child: &mut Node<Index, K, V>) -> Index {
-        new_child.next = self.child;
-        self.child = Some(new_child.index.unwrap());
-        self.index.unwrap()
-    }
-
-    fn link(&mut self, other: &mut Self, compare: fn(lhs: &K, rhs: &K) -> bool) -> Index {
-        if compare(&self.key, &other.key) {
-            self.add_child(other)
-        } else {
-            other.add_child(self)
-        }
-    }
-
-    fn ranked_link(&mut self, other: &mut Self, compare: fn(lhs: &K, rhs: &K) -> bool) -> Index {
-        assert!(self.rank == other.rank);
-        if compare(&self.key, &other.key) {
-            self.rank += 1;
-            self.add_child(other)
-        } else {
-            other.rank += 1;
-            other.add_child(self)
-        }
-    }
-
-    pub fn is_hollow(&self) -> bool {
-        self.item.is_none()
-    }
-}
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::cmp;
+use core::fmt;
+use core::ops;
+
+#[cfg(feature = "std")]
+mod addressable;
+mod error;
+mod fixed;
+mod float;
+mod forest;
+mod handle_map;
+mod indexed;
+mod keyed;
+mod slab;
+mod store;
+
+#[cfg(feature = "std")]
+pub use addressable::{AddressableHollowHeap, Outcome};
+pub use error::UpdateError;
+pub use fixed::FixedHollowHeap;
+pub use float::{
+    max_heap_compare_nan_first, max_heap_compare_nan_last, max_heap_total_cmp,
+    min_heap_compare_nan_first, min_heap_compare_nan_last, min_heap_total_cmp, Float,
+};
+pub use forest::{HeapForest, HeapId};
+pub use handle_map::HandleMap;
+pub use indexed::IndexedHollowHeap;
+pub use keyed::{IntoIter as KeyedIntoIter, KeyedHollowHeap};
+pub use slab::Handle;
+
+use forest::HeapSets;
+use slab::Slot;
+use store::{Dag, Links};
+
+/// By default the heap is rebuilt once there are more than twice as many hollow nodes as elements.
+const DEFAULT_REBUILD_RATIO: f32 = 2.0;
+
+/// Upper bound (exclusive) on the rank of any node, see `Links::rank`.
+const MAX_RANK: usize = 93;
 
 /// The comparison function used for the min heap.
 pub fn min_heap_compare<K: PartialOrd>(lhs: &K, rhs: &K) -> bool {
@@ -164,13 +190,45 @@ pub fn max_heap_compare<K: PartialOrd>(lhs: &K, rhs: &K) -> bool {
 /// [See the module-level documentation for example usage and motivation.](./index.html)
 #[derive(Clone)]
 pub struct HollowHeap<K, V> {
-    dag: Arena<Node<Index, K, V>>,
-    dag_root: Option<Index>,
-    pub compare: fn(&K, &K) -> bool,
-    pub derive_key: fn(&V) -> K,
+    dag: Dag<K, V>,
+    /// The best root.
+    dag_root: Option<Slot>,
+    /// All roots threaded through their `next` links when linking lazily, `None` otherwise.
+    root_list: Option<Slot>,
+    /// Scratch space for `delete`, kept around so that popping does not allocate.
+    roots_by_rank: Vec<Option<Slot>>,
+    /// Rebuild once the ratio of hollow nodes to elements exceeds this. `None` disables rebuilds.
+    rebuild_ratio: Option<f32>,
+    /// Whether to use the one-parent variant, see `HollowHeapBuilder::one_parent`.
+    one_parent: bool,
+    /// Whether to link roots only in `delete`, see `HollowHeapBuilder::lazy_linking`.
+    lazy_linking: bool,
+    /// Whether to pop equal keys in insertion order, see `HollowHeapBuilder::stable`.
+    stable: bool,
+    /// The sequence number of the next pushed element when `stable`.
+    next_sequence: u64,
+    /// Added to every stored key to get the actual key, see `add_to_all_keys`.
+    offset: Option<Offset<K>>,
+    /// The sets of melded heaps and their offsets for the inner heap of a `HeapForest`, `None`
+    /// for all other heaps, see `settle`.
+    sets: Option<HeapSets<K>>,
+    /// Keys for which this returns `false` are rejected, see `HollowHeapBuilder::reject_nan`.
+    validate_key: Option<fn(&K) -> bool>,
+    compare: fn(&K, &K) -> bool,
+    /// `None` for the inner heaps of `KeyedHollowHeap`, `AddressableHollowHeap` and
+    /// `IndexedHollowHeap`, which are always given the keys, see `derive`.
+    derive_key: Option<fn(&V) -> K>,
+}
+
+/// An offset that is added to all keys of a heap, together with the arithmetic of the key type so
+/// that it can be applied without requiring `Add` and `Sub` everywhere.
+#[derive(Clone)]
+struct Offset<K> {
+    value: K,
+    add: fn(&K, &K) -> K,
+    sub: fn(&K, &K) -> K,
 }
 
-use std::fmt;
 impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for HollowHeap<K, V> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -181,254 +239,1130 @@ impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for HollowHeap<K, V> {
     }
 }
 
-impl<K: PartialOrd + fmt::Debug, V> HollowHeap<K, V> {
-    pub fn new(compare: fn(&K, &K) -> bool, derive_key: fn(&V) -> K) -> HollowHeap<K, V> {
+impl<K, V> HollowHeap<K, V> {
+    fn with_dag(
+        dag: Dag<K, V>,
+        compare: fn(&K, &K) -> bool,
+        derive_key: Option<fn(&V) -> K>,
+    ) -> HollowHeap<K, V> {
         HollowHeap {
-            dag: Arena::new(),
+            dag,
             dag_root: None,
+            root_list: None,
+            roots_by_rank: Vec::new(),
+            rebuild_ratio: Some(DEFAULT_REBUILD_RATIO),
+            one_parent: false,
+            lazy_linking: false,
+            stable: false,
+            next_sequence: 0,
+            offset: None,
+            sets: None,
+            validate_key: None,
             compare,
             derive_key,
         }
     }
 
+    /// An empty heap with room for `n` elements and the same configuration as this one, except
+    /// for the key functions, the offset and the key validation.
+    fn empty_with<K2, V2>(
+        &self,
+        n: usize,
+        compare: fn(&K2, &K2) -> bool,
+        derive_key: Option<fn(&V2) -> K2>,
+    ) -> HollowHeap<K2, V2> {
+        let mut heap = HollowHeap::with_dag(Dag::with_capacity(n), compare, derive_key);
+        heap.rebuild_ratio = self.rebuild_ratio;
+        heap.one_parent = self.one_parent;
+        heap.lazy_linking = self.lazy_linking;
+        heap.stable = self.stable;
+        heap.next_sequence = self.next_sequence;
+        heap
+    }
+
+    /// The `derive_key` function. Every heap created through the public API has one, while the
+    /// heaps without one are only used through operations that take the keys, so this never
+    /// fails.
+    fn derive(&self) -> fn(&V) -> K {
+        self.derive_key
+            .expect("Heaps without `derive_key` are always given the keys.")
+    }
+}
+
+impl<K: PartialOrd, V> HollowHeap<K, V> {
+    pub fn new(compare: fn(&K, &K) -> bool, derive_key: fn(&V) -> K) -> HollowHeap<K, V> {
+        HollowHeap::with_dag(Dag::new(), compare, Some(derive_key))
+    }
+
     /// Test whether there are any elements in the heap.
     pub fn is_empty(&self) -> bool {
-        self.dag.len() == 0
+        self.len() == 0
+    }
+
+    /// The number of elements in the heap, not counting hollow nodes.
+    pub fn len(&self) -> usize {
+        self.dag.full_len()
     }
 
     /// Push a value into the heap.
     ///
-    /// Returns the index of the pushed element.
-    pub fn push(&mut self, value: V) -> Index {
-        let key = (self.derive_key)(&value);
+    /// Returns the handle of the pushed element.
+    pub fn push(&mut self, value: V) -> Handle {
+        let key = self.derive()(&value);
         self.push_with_key(value, key)
     }
 
     /// Push a value into the heap with the provided key.
     ///
     /// Circumvents the `derive_key` function and is thus not recommended.
-    pub fn push_with_key(&mut self, value: V, key: K) -> Index {
-        let index = Node::new_in_arena(&mut self.dag, value, key);
-        if let Some(root_index) = self.dag_root {
-            let (root, node) = self.dag.get2_mut(root_index, index);
-            // unwrap should be safe because these indices come from inside the dag
-            self.dag_root = Some(root.unwrap().link(node.unwrap(), self.compare));
+    ///
+    /// Asserts that the key is not rejected, see
+    /// [`HollowHeapBuilder::reject_nan`](./struct.HollowHeapBuilder.html#method.reject_nan), and
+    /// not below the offset, see `add_to_all_keys`.
+    pub fn push_with_key(&mut self, value: V, key: K) -> Handle {
+        assert!(
+            self.check_key(&key).is_ok(),
+            "Should not be pushing a rejected key or a key below the offset, consider `try_push` \
+             instead."
+        );
+        let key = self.stored_key(key);
+        let slot = self.push_node(value, key);
+        self.dag.handle(slot)
+    }
+
+    /// Push a value into the heap unless its key is rejected, see
+    /// [`HollowHeapBuilder::reject_nan`](./struct.HollowHeapBuilder.html#method.reject_nan), or
+    /// below the offset, see `add_to_all_keys`.
+    ///
+    /// Returns the handle of the pushed element or gives back the value if its key was rejected.
+    pub fn try_push(&mut self, value: V) -> Result<Handle, V> {
+        let key = self.derive()(&value);
+        self.try_push_with_key(value, key)
+    }
+
+    /// Push a value into the heap with the provided key unless the key is rejected.
+    ///
+    /// Circumvents the `derive_key` function and is thus not recommended.
+    pub fn try_push_with_key(&mut self, value: V, key: K) -> Result<Handle, V> {
+        if self.check_key(&key).is_err() {
+            return Err(value);
+        }
+        let key = self.stored_key(key);
+        let slot = self.push_node(value, key);
+        Ok(self.dag.handle(slot))
+    }
+
+    /// Check that the heap accepts the actual `key`, and that it can be stored relative to the
+    /// offset, which has to be a lower bound of the keys, see `add_to_all_keys`.
+    fn check_key(&self, key: &K) -> Result<(), UpdateError> {
+        if !self
+            .validate_key
+            .is_none_or(|validate_key| validate_key(key))
+        {
+            return Err(UpdateError::RejectedKey);
+        }
+        match &self.offset {
+            Some(offset) if *key < offset.value => Err(UpdateError::BelowOffset),
+            _ => Ok(()),
+        }
+    }
+
+    /// The key to store in the dag for the actual `key`.
+    fn stored_key(&self, key: K) -> K {
+        match &self.offset {
+            Some(offset) => (offset.sub)(&key, &offset.value),
+            None => key,
+        }
+    }
+
+    /// The actual key for a key stored in the dag.
+    fn actual_key(&self, key: K) -> K {
+        match &self.offset {
+            Some(offset) => (offset.add)(&key, &offset.value),
+            None => key,
+        }
+    }
+
+    /// Insert a node with an already stored `key`, recording the set it belongs to in a forest.
+    fn insert_node(&mut self, item: V, key: K) -> Slot {
+        let slot = self.dag.insert(item, key);
+        if let Some(sets) = &mut self.sets {
+            sets.record(slot);
+        }
+        slot
+    }
+
+    /// Rebase the stored key of the node at `slot` to the offset of the heap being operated on.
+    ///
+    /// In a `HeapForest`, keys are stored relative to the offset of the heap they were pushed
+    /// into, and melded heaps keep their offsets relative to each other. Nodes are settled before their keys are
+    /// compared or returned, which takes amortized O(log h) time for the h heaps melded into each
+    /// other. Other heaps have a single offset, so there is nothing to settle.
+    fn settle(&mut self, slot: Slot) {
+        if let Some(sets) = &mut self.sets {
+            if !self.dag.is_hollow(slot) {
+                sets.settle(slot, self.dag.key_mut(slot));
+            }
+        }
+    }
+
+    fn push_node(&mut self, value: V, key: K) -> Slot {
+        let slot = self.insert_node(value, key);
+        if self.stable {
+            self.dag.set_sequence(slot, self.next_sequence);
+            self.next_sequence += 1;
+        }
+        self.add_root(slot);
+        slot
+    }
+
+    /// Link the node at `slot` with the root, or only add it to the list of roots when linking
+    /// lazily.
+    fn add_root(&mut self, slot: Slot) {
+        let root = match self.dag_root {
+            Some(root) => root,
+            None => {
+                self.dag_root = Some(slot);
+                if self.lazy_linking {
+                    self.dag[slot].next = None;
+                    self.root_list = Some(slot);
+                }
+                return;
+            }
+        };
+        if self.lazy_linking {
+            self.dag[slot].next = self.root_list;
+            self.root_list = Some(slot);
+            if !self.is_better(root, slot) {
+                self.dag_root = Some(slot);
+            }
         } else {
-            self.dag_root = Some(index);
+            self.dag_root = Some(self.link(root, slot));
         }
-        index
     }
 
-    /// Increase or decrease the key (used for sorting) of the Node at `index`.
+    /// Link the list of roots of a lazily linking heap into a single tree by rank, like
+    /// `delete_node` links them when the root is deleted. Has to happen before a node other than
+    /// the root is changed or made hollow.
+    fn link_roots(&mut self) {
+        if let Some(root) = self.root_list {
+            if self.dag[root].next.is_some() {
+                self.link_by_rank(None);
+            }
+        }
+    }
+
+    /// Increase or decrease the key (used for sorting) of the Node at `handle`.
     ///
     /// **Note:** This function only changes the key, not the item.
     ///
-    /// Expects (and asserts) `dag_root` to not be empty and `index` to be valid.
+    /// Expects (and asserts) `dag_root` to not be empty and `handle` to be valid.
     /// Asserts that `new_key` is greater (or smaller) than the old key (depending on the type
-    /// of heap).
-    pub fn change_key(&mut self, index: Index, new_key: K) -> Index {
-        self.update(index, None, new_key.into())
+    /// of heap). See `try_change_key` for a non-panicking version.
+    pub fn change_key(&mut self, handle: Handle, new_key: K) -> Handle {
+        self.update(handle, None, new_key)
+            .unwrap_or_else(|error| panic!("{}", error))
     }
 
-    /// Change the item (and recalculate the key) of the Node at `index`.
+    /// Change the item (and recalculate the key) of the Node at `handle`.
     ///
-    /// Expects (and asserts) `dag_root` to not be empty and `index` to be valid.
+    /// Expects (and asserts) `dag_root` to not be empty and `handle` to be valid.
     /// Asserts that the new generated key is greater (or smaller) than the old key (depending on
-    /// the type of heap).
-    pub fn change_item(&mut self, index: Index, new_item: V) -> Index {
-        self.update(index, new_item.into(), None)
+    /// the type of heap). See `try_change_item` for a non-panicking version.
+    pub fn change_item(&mut self, handle: Handle, new_item: V) -> Handle {
+        self.try_change_item(handle, new_item)
+            .unwrap_or_else(|error| panic!("{}", error))
     }
 
-    fn update(&mut self, index: Index, new_item: Option<V>, new_key: Option<K>) -> Index {
-        assert_ne!(
-            self.dag_root, None,
-            "Should not be trying to change key on empty heap."
-        );
-        let ref item_ref = &new_item;
-        let new_key = new_key.unwrap_or_else(|| {
-            (self.derive_key)(
-                item_ref
-                    .as_ref()
-                    .expect("Need either a new item or a new key to update."),
-            )
-        });
-        if self.dag_root == Some(index) {
-            // the changed value is the root so will be updated in-place
-            let ref mut node = self.dag[index];
-            assert!(
-                (self.compare)(&new_key, &node.key),
-                format!("Should only increase key to 'better' value. '{:?}' is not 'better' than '{:?}'", new_key, node.key)
-            );
-            if let Some(item) = new_item {
-                node.item = Some(item);
-            }
-            node.key = new_key.into();
-            return index;
-        }
-        // the changed value is not the root and thus will become hollow
-        // let (new_index, rank) = {
-        let node = self
-            .dag
-            .get_mut(index)
-            .expect("Should not be accessing the heap with an invalid index.");
-        assert!(
-            (self.compare)(&new_key, &node.key),
-            format!(
-                "Should only increase key to 'better' value. '{:?}' is not 'better' than '{:?}'",
-                new_key, node.key
-            )
-        );
-        let item = {
-            let old_item = node
-                .item
-                .take()
-                .expect("Should not be changing the key of an item twice.");
-            if let Some(item) = new_item {
-                item
-            } else {
-                old_item
+    /// Like `change_key`, but returns an error instead of panicking. The heap is unchanged if an
+    /// error is returned.
+    pub fn try_change_key(&mut self, handle: Handle, new_key: K) -> Result<Handle, UpdateError> {
+        self.update(handle, None, new_key)
+    }
+
+    /// Like `change_item`, but returns an error instead of panicking. The heap is unchanged and
+    /// `new_item` is dropped if an error is returned.
+    pub fn try_change_item(&mut self, handle: Handle, new_item: V) -> Result<Handle, UpdateError> {
+        let new_key = self.derive()(&new_item);
+        self.update(handle, Some(new_item), new_key)
+    }
+
+    /// Change the key of the element at `handle` to `new_key`, and its item to `new_item` if
+    /// given.
+    fn update(
+        &mut self,
+        handle: Handle,
+        new_item: Option<V>,
+        new_key: K,
+    ) -> Result<Handle, UpdateError> {
+        self.link_roots();
+        let (slot, new_key) = self.check_update(handle, new_key)?;
+        if self.is_root(slot) {
+            self.update_root(slot, new_item, new_key);
+            return Ok(handle);
+        }
+        let new_slot = self.replace_node(slot, new_item, new_key);
+        self.add_root(new_slot);
+        self.rebuild_if_too_hollow();
+        Ok(self.dag.handle(new_slot))
+    }
+
+    /// Mutate the item at `handle` in place with `f` and move the element according to the key
+    /// derived from the mutated item.
+    ///
+    /// A 'better' key takes the path of `change_item`, an equal key is stored in place and a
+    /// 'worse' key removes the element and pushes it again, which takes O(log n) amortized time.
+    /// Returns the new handle of the element.
+    ///
+    /// Errors found before `f` is called leave the heap unchanged and come without an item. If
+    /// the heap rejects the derived key (see
+    /// [`HollowHeapBuilder::reject_nan`](./struct.HollowHeapBuilder.html#method.reject_nan)) or it
+    /// is below the offset (see `add_to_all_keys`), the mutated item cannot be kept with its old
+    /// key, so the element is removed and its item is returned with the error. See
+    /// `try_update_with` for leaving the element unchanged instead.
+    pub fn update_with<F: FnOnce(&mut V)>(
+        &mut self,
+        handle: Handle,
+        f: F,
+    ) -> Result<Handle, (UpdateError, Option<V>)> {
+        let slot = self
+            .check_update_with(handle)
+            .map_err(|error| (error, None))?;
+        let derive_key = self.derive();
+        let item = self.dag.item_mut(slot).expect("The node is full.");
+        f(item);
+        let new_key = derive_key(item);
+        if let Err(error) = self.check_key(&new_key) {
+            let (item, _) = self.dag.make_hollow(slot).expect("The node is full.");
+            self.delete_node(slot);
+            return Err((error, Some(item)));
+        }
+        Ok(self.move_to_key(handle, slot, new_key))
+    }
+
+    /// Like `update_with`, but the heap is unchanged, including the element, if an error is
+    /// returned.
+    ///
+    /// If the heap rejects keys or has an offset, `f` mutates a copy of the item, which only
+    /// replaces the item once its key is accepted. Otherwise the item is mutated in place like in
+    /// `update_with`, which cannot fail after calling `f` then.
+    pub fn try_update_with<F: FnOnce(&mut V)>(
+        &mut self,
+        handle: Handle,
+        f: F,
+    ) -> Result<Handle, UpdateError>
+    where
+        V: Clone,
+    {
+        if self.validate_key.is_none() && self.offset.is_none() {
+            return self.update_with(handle, f).map_err(|(error, _)| error);
+        }
+        let slot = self.check_update_with(handle)?;
+        let mut new_item = self.dag.item(slot).expect("The node is full.").clone();
+        f(&mut new_item);
+        let new_key = self.derive()(&new_item);
+        self.check_key(&new_key)?;
+        *self.dag.item_mut(slot).expect("The node is full.") = new_item;
+        Ok(self.move_to_key(handle, slot, new_key))
+    }
+
+    /// Check that the element at `handle` may be updated, returning its slot.
+    fn check_update_with(&mut self, handle: Handle) -> Result<Slot, UpdateError> {
+        if self.dag_root.is_none() {
+            return Err(UpdateError::EmptyHeap);
+        }
+        self.link_roots();
+        let slot = self.dag.slot(handle).ok_or(UpdateError::InvalidHandle)?;
+        if self.dag.is_hollow(slot) {
+            return Err(UpdateError::Hollow);
+        }
+        Ok(slot)
+    }
+
+    /// Move the element at `slot` according to its accepted `new_key`, which may be better,
+    /// equal or worse than its old key, and return its new handle.
+    fn move_to_key(&mut self, handle: Handle, slot: Slot, new_key: K) -> Handle {
+        let new_key = self.stored_key(new_key);
+        self.settle(slot);
+        if (self.compare)(&new_key, self.dag.key(slot)) {
+            if self.is_root(slot) {
+                self.update_root(slot, None, new_key);
+                return handle;
             }
-        };
-        let rank = node.rank;
-
-        // };
-        let new_index = self.push_with_key(item, new_key);
-        let second_parent = {
-            // we created a node and got the new index, so this access is fine
-            let ref mut new_node = self.dag[new_index];
-            new_node.rank = if rank > 1 { rank - 2 } else { 0 };
-            if self.dag_root == Some(new_index) {
-                None
-            } else {
-                new_node.child = Some(index);
-                Some(new_index)
+            let new_slot = self.replace_node(slot, None, new_key);
+            self.add_root(new_slot);
+            self.rebuild_if_too_hollow();
+            return self.dag.handle(new_slot);
+        }
+        if !(self.compare)(self.dag.key(slot), &new_key) {
+            // neither better nor worse, so the heap order holds as it is
+            *self.dag.key_mut(slot) = new_key;
+            return handle;
+        }
+        let sequence = self.stable.then(|| self.dag.sequence(slot));
+        let (item, _) = self.dag.make_hollow(slot).expect("The node is full.");
+        self.delete_node(slot);
+        self.push_moved(item, new_key, sequence)
+    }
+
+    /// Change the keys of several elements at once. Like calling `try_change_key` for every pair
+    /// of handle and new key, but the new nodes are linked among themselves before the result is
+    /// linked with the root, so the root is only touched once.
+    ///
+    /// The whole batch is validated up front, and the heap is unchanged if an error is returned.
+    /// A handle occurring twice in the batch is reported as `UpdateError::DuplicateHandle`.
+    /// Returns the new handles in the order of the batch.
+    pub fn change_keys<I: IntoIterator<Item = (Handle, K)>>(
+        &mut self,
+        changes: I,
+    ) -> Result<Vec<Handle>, UpdateError> {
+        self.link_roots();
+        let mut checked = Vec::new();
+        for (handle, new_key) in changes {
+            checked.push(self.check_update(handle, new_key)?);
+        }
+        let mut slots: Vec<Slot> = checked.iter().map(|(slot, _)| *slot).collect();
+        slots.sort_unstable();
+        if slots.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(UpdateError::DuplicateHandle);
+        }
+        let mut handles = Vec::with_capacity(checked.len());
+        let mut batch_root = None;
+        for (slot, new_key) in checked {
+            if self.is_root(slot) {
+                self.update_root(slot, None, new_key);
+                handles.push(self.dag.handle(slot));
+                continue;
             }
-        };
-        // `index` is assumed valid; the expect above guarantees that this is the case
-        self.dag[index].second_parent = second_parent;
-        new_index
+            let new_slot = self.replace_node(slot, None, new_key);
+            handles.push(self.dag.handle(new_slot));
+            batch_root = Some(match batch_root {
+                Some(batch_root) => self.link(batch_root, new_slot),
+                None => new_slot,
+            });
+        }
+        if let Some(batch_root) = batch_root {
+            self.add_root(batch_root);
+            self.rebuild_if_too_hollow();
+        }
+        Ok(handles)
+    }
+
+    /// Check that the element at `handle` may get `new_key`, returning its slot and the key to
+    /// store.
+    fn check_update(&mut self, handle: Handle, new_key: K) -> Result<(Slot, K), UpdateError> {
+        if self.dag_root.is_none() {
+            return Err(UpdateError::EmptyHeap);
+        }
+        let slot = self.dag.slot(handle).ok_or(UpdateError::InvalidHandle)?;
+        if self.dag.is_hollow(slot) {
+            return Err(UpdateError::Hollow);
+        }
+        self.check_key(&new_key)?;
+        let new_key = self.stored_key(new_key);
+        self.settle(slot);
+        if !(self.compare)(&new_key, self.dag.key(slot)) {
+            return Err(UpdateError::NotBetter);
+        }
+        Ok((slot, new_key))
+    }
+
+    /// Whether the node at `slot` is the root. Lazily linking heaps only have one root left once
+    /// `link_roots` ran.
+    fn is_root(&self, slot: Slot) -> bool {
+        self.dag_root == Some(slot)
+    }
+
+    /// Change the root at `slot` in place.
+    fn update_root(&mut self, slot: Slot, new_item: Option<V>, new_key: K) {
+        if let Some(item) = new_item {
+            *self.dag.item_mut(slot).expect("The node is full.") = item;
+        }
+        *self.dag.key_mut(slot) = new_key;
+        let best = self.dag_root.expect("Roots exist in a non-empty heap.");
+        self.settle(best);
+        if self.is_better(slot, best) {
+            self.dag_root = Some(slot);
+        }
+    }
+
+    /// Make the node at `slot`, which is not a root, hollow and move its element to a new node
+    /// with `new_key`. Returns the slot of the new node, which still has to be linked.
+    fn replace_node(&mut self, slot: Slot, new_item: Option<V>, new_key: K) -> Slot {
+        let (old_item, _) = self.dag.make_hollow(slot).expect("The node is full.");
+        let item = new_item.unwrap_or(old_item);
+        let rank = self.dag[slot].rank;
+
+        let new_slot = self.insert_node(item, new_key);
+        if self.stable {
+            // the changed element keeps its place among equal keys
+            let sequence = self.dag.sequence(slot);
+            self.dag.set_sequence(new_slot, sequence);
+        }
+        self.dag[new_slot].rank = if rank > 1 { rank - 2 } else { 0 };
+        if self.one_parent {
+            // the hollow node keeps its first two children and hands the rest to the new node
+            let second_child = self.dag[slot].child.and_then(|child| self.dag[child].next);
+            if let Some(second_child) = second_child {
+                self.dag[new_slot].child = self.dag[second_child].next.take();
+            }
+        } else {
+            // the hollow node becomes the last child of the new node, see `delete_node`
+            self.dag[new_slot].child = Some(slot);
+            self.dag[slot].second_parent = Some(new_slot);
+        }
+        new_slot
     }
 
     /// Have a look at the top-most value of the heap.
     ///
     /// Returns `None` if the heap is empty.
     pub fn peek(&self) -> Option<&V> {
-        self.dag_root
-            .map(|root_index| self.dag[root_index].item.as_ref())
-            .unwrap_or(None)
+        self.dag_root.and_then(|root| self.dag.item(root))
     }
 
-    /// Remove the value at `index` from the heap.
+    /// Iterate over the values from best to worst without changing the heap.
     ///
-    /// Returns the new root index if successful and `None` if deletion failed or the heap is empty
-    /// after the operation.
-    pub fn delete(&mut self, index: Index) -> Option<Index> {
-        if self.dag_root != Some(index) {
-            if let Some(node) = self.dag.get_mut(index) {
-                node.item = None;
-                node.second_parent = None;
-                return self.dag_root;
-            }
-            // nothing todo if item is not present in dag
-            // println!("No element found to delete at {:?}", index);
-            return None;
+    /// Walks the dag best-first, keeping the children of the yielded nodes in a small binary heap
+    /// and skipping hollow nodes. Yielding a value takes O(d log m) time, where d is its number of
+    /// children and m the number of values that were reached but not yet yielded. So listing the
+    /// best k values is cheap, unless the heap had many pushes since the last `pop`, which leave
+    /// the root with many children.
+    pub fn iter_sorted(&self) -> SortedIter<'_, K, V> {
+        SortedIter::new(self)
+    }
+
+    /// The `n`-th best value, counting from zero, so `nth_best(0)` is the same as `peek`. See
+    /// `iter_sorted` for the cost.
+    ///
+    /// Returns `None` if the heap has at most `n` values.
+    pub fn nth_best(&self, n: usize) -> Option<&V> {
+        self.iter_sorted().nth(n)
+    }
+
+    /// Have a look at the key of the top-most value of the heap, including any offset added by
+    /// `add_to_all_keys`.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn peek_key(&self) -> Option<K>
+    where
+        K: Clone,
+    {
+        let root = self.dag_root?;
+        Some(self.actual_key(self.dag.key(root).clone()))
+    }
+
+    /// The stored key of the root, which ignores any offset.
+    pub(crate) fn peek_entry(&self) -> Option<(&K, &V)> {
+        let root = self.dag_root?;
+        Some((self.dag.key(root), self.dag.item(root)?))
+    }
+
+    /// Remove the value at `handle` from the heap.
+    ///
+    /// Returns the new root handle if successful and `None` if deletion failed or the heap is
+    /// empty after the operation.
+    pub fn delete(&mut self, handle: Handle) -> Option<Handle> {
+        self.link_roots();
+        let root = self.delete_node(self.dag.slot(handle)?);
+        root.map(|root| self.dag.handle(root))
+    }
+
+    /// Delete the node at `slot`, which may already be hollow. Expects the roots to be linked
+    /// unless `slot` is the root, see `link_roots`.
+    fn delete_node(&mut self, slot: Slot) -> Option<Slot> {
+        if self.dag_root != Some(slot) {
+            self.dag.make_hollow(slot);
+            self.rebuild_if_too_hollow();
+            return self.dag_root;
         }
-        // index is the root index from here
-        let root_index = index;
-        let mut max_rank = 0;
-        let mut roots_by_rank = vec![None];
-        if let Some(root) = self.dag.get_mut(root_index) {
-            root.next = None;
-            root.second_parent = None;
-        } else {
-            // println!("No root found to delete at {:?}", root_index);
-            return None;
+        // slot is the root from here
+        self.dag.make_hollow(slot);
+        self.dag[slot].second_parent = None;
+        self.link_by_rank(Some(slot));
+        // return the slot of the next root
+        self.dag_root
+    }
+
+    /// Link the full roots and the full children of the hollow ones by rank, and then the
+    /// remaining trees of different ranks into a single tree. The roots are the list of roots
+    /// when linking lazily, which includes the hollow `deleted` if given, and `deleted` otherwise.
+    fn link_by_rank(&mut self, deleted: Option<Slot>) {
+        if self.roots_by_rank.is_empty() {
+            self.roots_by_rank.resize(MAX_RANK, None);
         }
-        let mut queue = VecDeque::new();
-        queue.push_back(root_index);
-        let mut next_root = None;
-        while let Some(current_root) = queue.pop_front() {
-            let to_delete = current_root;
-            let mut next_child = {
-                let ref root = self.dag[current_root];
-                next_root = root.next;
-                root.child
-            };
-            while let Some(child_idx) = next_child {
-                next_child = self.dag[child_idx].next;
-                if self.dag[child_idx].is_hollow() {
-                    let ref mut current_child = self.dag[child_idx];
-                    match current_child.second_parent {
+        let mut max_rank = 0;
+        // the roots are walked like the children of a hollow node
+        let mut next_child = match deleted {
+            Some(slot) if !self.lazy_linking => {
+                self.dag[slot].next = None;
+                Some(slot)
+            }
+            _ => self.root_list,
+        };
+        // Hollow roots still to be deleted are threaded through their `next` links, so no queue
+        // is needed.
+        let mut hollow_roots = None;
+        let mut deleting = None;
+        loop {
+            while let Some(child) = next_child {
+                next_child = self.dag[child].next;
+                if self.dag.is_hollow(child) {
+                    match self.dag[child].second_parent {
                         None => {
-                            current_child.next = next_root;
-                            next_root = Some(current_child.index.unwrap());
+                            self.dag[child].next = hollow_roots;
+                            hollow_roots = Some(child);
                         }
-                        Some(_) => {
-                            if current_child.second_parent == Some(to_delete) {
+                        Some(second_parent) => {
+                            if Some(second_parent) == deleting {
                                 next_child = None;
                             } else {
-                                current_child.next = None;
+                                self.dag[child].next = None;
                             }
-                            current_child.second_parent = None;
+                            self.dag[child].second_parent = None;
                         }
                     }
                 } else {
-                    let mut cur_child_idx = child_idx;
-                    let mut rank = self.dag[cur_child_idx].rank;
-                    if rank as usize >= roots_by_rank.len() {
-                        roots_by_rank.resize((rank + 1) as usize, None);
-                    }
-                    while let Some(index) = roots_by_rank[rank as usize] {
-                        let (first_node, second_node) = self.dag.get2_mut(index, cur_child_idx);
-                        // unwrap should be safe because these indices come from inside the dag
-                        cur_child_idx = first_node
-                            .unwrap()
-                            .ranked_link(&mut second_node.unwrap(), self.compare);
-                        roots_by_rank[rank as usize] = None;
-                        rank = rank + 1;
-                        if rank as usize >= roots_by_rank.len() {
-                            roots_by_rank.push(None);
-                        }
+                    let mut current = child;
+                    let mut rank = self.dag[current].rank as usize;
+                    while let Some(other) = self.roots_by_rank[rank].take() {
+                        current = self.ranked_link(other, current);
+                        rank += 1;
                     }
                     // the ranked_link increased the rank
                     max_rank = cmp::max(rank, max_rank);
-                    roots_by_rank.resize((max_rank + 1) as usize, None);
-                    roots_by_rank[rank as usize] = Some(cur_child_idx);
+                    self.roots_by_rank[rank] = Some(current);
                 }
             }
-            next_root.map(|next_index| queue.push_back(next_index));
-            self.dag.remove(to_delete);
-        }
-        for root_idx in roots_by_rank {
-            root_idx.map(|root_index| {
-                match next_root {
-                    None => next_root = Some(root_index),
-                    Some(next_root_index) => {
-                        let (root, other_root) = self.dag.get2_mut(next_root_index, root_index);
-                        // unwrap should be safe because these indices come from inside the dag
-                        next_root = Some(root.unwrap().link(other_root.unwrap(), self.compare));
-                    }
-                }
-            });
+            if let Some(deleted) = deleting {
+                self.dag.remove(deleted);
+            }
+            let to_delete = match hollow_roots {
+                Some(to_delete) => to_delete,
+                None => break,
+            };
+            hollow_roots = self.dag[to_delete].next;
+            next_child = self.dag[to_delete].child;
+            deleting = Some(to_delete);
+        }
+        let mut tree = None;
+        for rank in 0..=max_rank {
+            if let Some(root) = self.roots_by_rank[rank].take() {
+                tree = Some(match tree {
+                    Some(tree) => self.link(tree, root),
+                    None => root,
+                });
+            }
+        }
+        self.dag_root = tree;
+        self.root_list = None;
+        if let (Some(tree), true) = (tree, self.lazy_linking) {
+            self.dag[tree].next = None;
+            self.root_list = Some(tree);
         }
-        self.dag_root = next_root;
-        // return the index of the next root
-        next_root
     }
 
     /// Remove the top-most value from the heap and return it.
     ///
     /// Returns `None` if the heap is empty.
     pub fn pop(&mut self) -> Option<V> {
-        let (result, new_root_idx) = self
-            .dag_root
-            .map(|root_index| {
-                let item = self.dag[root_index].item.take();
-                (item, self.delete(root_index))
-            })
-            .unwrap_or((None, None));
-        self.dag_root = new_root_idx;
-        result
+        self.pop_entry().map(|(_, item)| item)
+    }
+
+    /// Remove the top-most value from the heap and return it together with its key, including any
+    /// offset added by `add_to_all_keys`.
+    ///
+    /// Returns `None` if the heap is empty.
+    pub fn pop_entry(&mut self) -> Option<(K, V)> {
+        let root = self.dag_root?;
+        self.settle(root);
+        let entry = self.dag.make_hollow(root);
+        self.delete_node(root);
+        entry.map(|(item, key)| (self.actual_key(key), item))
+    }
+
+    /// Pop values from the top of the heap as long as `predicate` holds for their key (including
+    /// any offset added by `add_to_all_keys`) and value.
+    ///
+    /// The returned iterator is lazy: it stops at the first value for which `predicate` fails,
+    /// which stays in the heap, and values are only popped when the iterator is advanced.
+    pub fn pop_while<F: FnMut(&K, &V) -> bool>(&mut self, predicate: F) -> PopWhile<'_, K, V, F> {
+        PopWhile {
+            heap: self,
+            predicate,
+            done: false,
+        }
+    }
+
+    /// Pop up to `n` values from the top of the heap.
+    ///
+    /// The returned iterator is lazy, values are only popped when it is advanced.
+    pub fn pop_n(&mut self, n: usize) -> PopN<'_, K, V> {
+        PopN { heap: self, n }
+    }
+
+    /// Pop all values whose key is not worse than `key` according to the compare function, e.g.
+    /// the keys at most `key` for a min heap. Keys include any offset added by
+    /// `add_to_all_keys`.
+    ///
+    /// The returned iterator is lazy, values are only popped when it is advanced.
+    pub fn pop_until(&mut self, key: K) -> PopUntil<'_, K, V> {
+        PopUntil { heap: self, key }
+    }
+
+    /// Whether the heap is not empty and `predicate` holds for the actual key and the value of the
+    /// top-most element.
+    fn top_satisfies<F: FnOnce(&K, &V) -> bool>(&self, predicate: F) -> bool {
+        match self.peek_entry() {
+            Some((key, item)) => self.with_actual_key(key, |key| predicate(key, item)),
+            None => false,
+        }
+    }
+
+    /// Remove the element at `handle` and return its key and item, unless the handle is stale.
+    #[cfg(feature = "std")]
+    pub(crate) fn remove_entry(&mut self, handle: Handle) -> Option<(K, V)> {
+        self.link_roots();
+        let slot = self.dag.slot(handle)?;
+        self.settle(slot);
+        let (item, key) = self.dag.make_hollow(slot)?;
+        self.delete_node(slot);
+        Some((self.actual_key(key), item))
+    }
+
+    /// The item at `handle` unless the handle is stale.
+    #[cfg(feature = "std")]
+    pub(crate) fn get(&self, handle: Handle) -> Option<&V> {
+        self.dag.item(self.dag.slot(handle)?)
+    }
+
+    /// Remove all values from the heap.
+    ///
+    /// Keeps the allocated capacity as well as the `compare` and `derive_key` functions.
+    pub fn clear(&mut self) {
+        self.dag.clear();
+        self.dag_root = None;
+        self.root_list = None;
+        self.next_sequence = 0;
+        self.offset = None;
+    }
+
+    /// Move all elements of `other` into this heap.
+    ///
+    /// Takes O(m) time for the m elements and hollow nodes of `other`, as the heaps do not share
+    /// their storage. Keys are rebased from the offset of `other` to the offset of this heap while
+    /// moving, so offsets add no extra cost. Handles to elements of `other` are invalid
+    /// afterwards. Melding in O(1) time, including offsets, is only available for the heaps of a
+    /// [`HeapForest`](./struct.HeapForest.html), which share their storage.
+    ///
+    /// If both heaps are stable, the elements of `other` count as pushed after the elements of
+    /// this heap, in the order they were pushed into `other`.
+    ///
+    /// Asserts that this heap accepts the keys of `other`, see `try_meld`.
+    pub fn meld(&mut self, mut other: HollowHeap<K, V>) {
+        assert!(
+            self.try_meld(&mut other).is_ok(),
+            "Should not be melding in a rejected key, consider `try_meld` instead."
+        );
+    }
+
+    /// Like `meld`, but leaves `other` empty instead of consuming it. Returns
+    /// `UpdateError::RejectedKey` and leaves both heaps unchanged if this heap rejects any key of
+    /// `other`, see
+    /// [`HollowHeapBuilder::reject_nan`](./struct.HollowHeapBuilder.html#method.reject_nan), and
+    /// `UpdateError::BelowOffset` if any key of `other` is below the offset of this heap, see
+    /// `add_to_all_keys`.
+    pub fn try_meld(&mut self, other: &mut HollowHeap<K, V>) -> Result<(), UpdateError> {
+        other.link_roots();
+        let nodes = other.nodes();
+        if self.validate_key.is_some() || self.offset.is_some() {
+            for slot in nodes.iter() {
+                if !other.dag.is_hollow(*slot) {
+                    other.with_actual_key(other.dag.key(*slot), |key| self.check_key(key))?;
+                }
+            }
+        }
+        let keep_sequences = self.stable && other.stable;
+        for slot in nodes {
+            let sequence = keep_sequences.then(|| self.next_sequence + other.dag.sequence(slot));
+            if let Some((item, key)) = other.dag.make_hollow(slot) {
+                let key = self.stored_key(other.actual_key(key));
+                if sequence.is_some() {
+                    self.push_moved(item, key, sequence);
+                } else {
+                    self.push_node(item, key);
+                }
+            }
+            other.dag.remove(slot);
+        }
+        if keep_sequences {
+            self.next_sequence += other.next_sequence;
+        }
+        // all nodes of `other` were removed, and clearing its dag would walk every slot it ever had
+        other.dag_root = None;
+        other.root_list = None;
+        other.next_sequence = 0;
+        other.offset = None;
+        Ok(())
+    }
+
+    /// Move the `k` best elements (or all of them if there are fewer) into a new heap with the
+    /// same configuration, e.g. the same `compare` and `derive_key` functions.
+    ///
+    /// Takes O(k log n) time. Returns the new heap and the new handles of the moved elements,
+    /// handles to the remaining elements stay valid. Keys are moved including any offset added by
+    /// `add_to_all_keys`, the new heap starts without an offset.
+    pub fn split_top(&mut self, k: usize) -> (HollowHeap<K, V>, HandleMap) {
+        let mut top = self.empty_like();
+        let mut handles = HandleMap::with_capacity(cmp::min(k, self.dag.full_len()));
+        while handles.len() < k {
+            let root = match self.dag_root {
+                Some(root) => root,
+                None => break,
+            };
+            let handle = self.dag.handle(root);
+            let sequence = self.stable.then(|| self.dag.sequence(root));
+            let (key, item) = self.pop_entry().expect("The heap is not empty.");
+            handles.insert(handle, top.push_moved(item, key, sequence));
+        }
+        (top, handles.build())
+    }
+
+    /// Move all elements whose key satisfies `predicate` into a new heap with the same
+    /// configuration, e.g. the same `compare` and `derive_key` functions.
+    ///
+    /// `predicate` is called with the actual keys, including any offset added by
+    /// `add_to_all_keys`, and the new heap starts without an offset. Takes O(n) time where n is
+    /// the number of elements and hollow nodes in the heap, as the remaining elements are
+    /// compacted if any were moved. Returns the new heap and the new handles of the moved
+    /// elements, handles to the remaining elements stay valid.
+    pub fn split_off<F: FnMut(&K) -> bool>(
+        &mut self,
+        mut predicate: F,
+    ) -> (HollowHeap<K, V>, HandleMap) {
+        self.link_roots();
+        let mut split = self.empty_like();
+        let mut handles = HandleMap::default();
+        for slot in self.nodes() {
+            if self.dag.is_hollow(slot) {
+                continue;
+            }
+            if !self.with_actual_key(self.dag.key(slot), &mut predicate) {
+                continue;
+            }
+            let handle = self.dag.handle(slot);
+            let sequence = self.stable.then(|| self.dag.sequence(slot));
+            let (item, key) = self.dag.make_hollow(slot).expect("The node is full.");
+            let key = self.actual_key(key);
+            handles.insert(handle, split.push_moved(item, key, sequence));
+        }
+        if !handles.is_empty() {
+            // the moved elements left hollow nodes anywhere in the dag, possibly the root
+            self.compact();
+        }
+        (split, handles.build())
+    }
+
+    /// Call `f` with the actual key for the stored `key`, without requiring `K: Clone`.
+    fn with_actual_key<R, F: FnOnce(&K) -> R>(&self, key: &K, f: F) -> R {
+        match &self.offset {
+            Some(offset) => f(&(offset.add)(key, &offset.value)),
+            None => f(key),
+        }
+    }
+
+    /// An empty heap with the same configuration as this one, but without an offset.
+    fn empty_like(&self) -> HollowHeap<K, V> {
+        let mut heap = self.empty_with(0, self.compare, self.derive_key);
+        heap.validate_key = self.validate_key;
+        heap
+    }
+
+    /// Convert the heap into one with other items, whose keys are derived with `derive_key` and
+    /// ordered by `compare`, by applying `f` to every item.
+    ///
+    /// Takes O(n) time where n is the number of elements and hollow nodes in the heap. The other
+    /// settings, except for `reject_nan`, carry over. Returns the new heap and the new handles of
+    /// all elements.
+    pub fn map<K2: PartialOrd, V2, F: FnMut(V) -> V2>(
+        self,
+        compare: fn(&K2, &K2) -> bool,
+        derive_key: fn(&V2) -> K2,
+        mut f: F,
+    ) -> (HollowHeap<K2, V2>, HandleMap) {
+        self.convert(compare, derive_key, |item, _| {
+            let item = f(item);
+            let key = derive_key(&item);
+            (item, key)
+        })
+    }
+
+    /// Convert the heap into one with other keys ordered by `compare` by applying `f` to every
+    /// key, including any offset added by `add_to_all_keys`. Values pushed later get their keys
+    /// from `derive_key`.
+    ///
+    /// Takes O(n) time where n is the number of elements and hollow nodes in the heap. The other
+    /// settings, except for `reject_nan`, carry over. Returns the new heap and the new handles of
+    /// all elements.
+    pub fn map_keys<K2: PartialOrd, F: FnMut(K) -> K2>(
+        self,
+        compare: fn(&K2, &K2) -> bool,
+        derive_key: fn(&V) -> K2,
+        mut f: F,
+    ) -> (HollowHeap<K2, V>, HandleMap) {
+        self.convert(compare, derive_key, |item, key| (item, f(key)))
+    }
+
+    /// Move every element into a new heap, converting its item and actual key with `f`.
+    fn convert<K2: PartialOrd, V2, F: FnMut(V, K) -> (V2, K2)>(
+        mut self,
+        compare: fn(&K2, &K2) -> bool,
+        derive_key: fn(&V2) -> K2,
+        mut f: F,
+    ) -> (HollowHeap<K2, V2>, HandleMap) {
+        self.link_roots();
+        let len = self.dag.full_len();
+        let mut converted = self.empty_with(len, compare, Some(derive_key));
+        let mut handles = HandleMap::with_capacity(len);
+        for slot in self.nodes() {
+            let sequence = self.stable.then(|| self.dag.sequence(slot));
+            if let Some((item, key)) = self.dag.make_hollow(slot) {
+                let (item, key) = f(item, self.actual_key(key));
+                let handle = converted.push_moved(item, key, sequence);
+                handles.insert(self.dag.handle(slot), handle);
+            }
+        }
+        (converted, handles.build())
+    }
+
+    /// Push an element with an already stored `key`, keeping its sequence number. Heaps created by
+    /// `empty_like` have no offset, so actual keys are stored as they are.
+    fn push_moved(&mut self, item: V, key: K, sequence: Option<u64>) -> Handle {
+        let slot = self.insert_node(item, key);
+        if let Some(sequence) = sequence {
+            self.dag.set_sequence(slot, sequence);
+        }
+        self.add_root(slot);
+        self.dag.handle(slot)
+    }
+
+    /// Drop all hollow nodes and relink the remaining elements into a single tree.
+    ///
+    /// Takes O(n) time where n is the number of elements and hollow nodes in the heap. Handles to
+    /// elements stay valid. Happens automatically when hollow nodes pile up, see
+    /// [`HollowHeapBuilder::with_rebuild_ratio`].
+    ///
+    /// [`HollowHeapBuilder::with_rebuild_ratio`]:
+    /// ./struct.HollowHeapBuilder.html#method.with_rebuild_ratio
+    pub fn compact(&mut self) {
+        if self.dag.hollow_len() == 0 {
+            return;
+        }
+        self.relink_all();
+    }
+
+    /// The function deciding whether a key is 'better' than another one.
+    pub fn compare(&self) -> fn(&K, &K) -> bool {
+        self.compare
+    }
+
+    /// The function deriving the key of a pushed value.
+    pub fn derive_key(&self) -> fn(&V) -> K {
+        self.derive()
+    }
+
+    /// Replace the compare function and rebuild the heap in O(n) time, where n is the number of
+    /// elements and hollow nodes in the heap. Handles to elements stay valid.
+    pub fn set_compare(&mut self, compare: fn(&K, &K) -> bool) {
+        self.link_roots();
+        self.compare = compare;
+        self.relink_all();
+    }
+
+    /// Replace the `derive_key` function and derive the keys of all elements anew, see
+    /// `rekey_all`.
+    ///
+    /// Returns `UpdateError::RejectedKey` or `UpdateError::BelowOffset` and leaves the heap and
+    /// its `derive_key` function unchanged if any of the new keys is rejected.
+    pub fn set_derive_key(&mut self, derive_key: fn(&V) -> K) -> Result<(), UpdateError> {
+        self.rekey_with(derive_key)?;
+        self.derive_key = Some(derive_key);
+        Ok(())
+    }
+
+    /// Derive the keys of all elements anew from their items and rebuild the heap in O(n) time,
+    /// where n is the number of elements and hollow nodes in the heap, e.g. after the result of
+    /// `derive_key` changed. Handles to elements stay valid.
+    ///
+    /// **Note:** This also replaces keys given by `change_key` or `push_with_key`.
+    ///
+    /// Returns `UpdateError::RejectedKey` and leaves the heap unchanged if any of the derived keys
+    /// is rejected, see
+    /// [`HollowHeapBuilder::reject_nan`](./struct.HollowHeapBuilder.html#method.reject_nan), and
+    /// `UpdateError::BelowOffset` if any is below the offset, see `add_to_all_keys`.
+    pub fn rekey_all(&mut self) -> Result<(), UpdateError> {
+        self.rekey_with(self.derive())
+    }
+
+    /// Replace the keys of all elements by the ones `derive_key` derives, after checking all of
+    /// them.
+    fn rekey_with(&mut self, derive_key: fn(&V) -> K) -> Result<(), UpdateError> {
+        self.link_roots();
+        let nodes = self.nodes();
+        let mut keys = Vec::with_capacity(self.dag.full_len());
+        for slot in nodes.iter() {
+            if let Some(item) = self.dag.item(*slot) {
+                let key = derive_key(item);
+                self.check_key(&key)?;
+                keys.push(key);
+            }
+        }
+        let mut keys = keys.into_iter();
+        for slot in nodes {
+            if self.dag.is_hollow(slot) {
+                continue;
+            }
+            let key = keys.next().expect("One key per full node.");
+            *self.dag.key_mut(slot) = self.stored_key(key);
+        }
+        self.relink_all();
+        Ok(())
+    }
+
+    /// Drop all hollow nodes and link the full nodes anew, which also restores the heap order
+    /// after keys or the compare function changed.
+    fn relink_all(&mut self) {
+        self.link_roots();
+        let nodes = self.nodes();
+        self.dag_root = None;
+        self.root_list = None;
+        for slot in nodes {
+            if self.dag.is_hollow(slot) {
+                self.dag.remove(slot);
+                continue;
+            }
+            self.dag[slot] = Links::NONE;
+            self.add_root(slot);
+        }
+    }
+
+    /// The slots of all nodes reachable from the roots, which are all nodes of the heap, collected
+    /// in O(n) time for its n elements and hollow nodes. Walking the slab instead would take time
+    /// in the most nodes the heap ever had. A hollow node with two parents is only collected
+    /// through its first parent, see `SortedIter::visit_children`.
+    fn nodes(&self) -> Vec<Slot> {
+        let mut nodes = Vec::new();
+        if self.lazy_linking {
+            let mut next_root = self.root_list;
+            while let Some(root) = next_root {
+                next_root = self.dag[root].next;
+                nodes.push(root);
+            }
+        } else if let Some(root) = self.dag_root {
+            nodes.push(root);
+        }
+        let mut index = 0;
+        while index < nodes.len() {
+            let parent = nodes[index];
+            let mut next_child = self.dag[parent].child;
+            while let Some(child) = next_child {
+                if self.dag[child].second_parent == Some(parent) {
+                    break;
+                }
+                next_child = self.dag[child].next;
+                nodes.push(child);
+            }
+            index += 1;
+        }
+        nodes
+    }
+
+    fn rebuild_if_too_hollow(&mut self) {
+        if let Some(ratio) = self.rebuild_ratio {
+            if self.dag.hollow_len() as f32 > ratio * self.dag.full_len() as f32 {
+                self.compact();
+            }
+        }
+    }
+
+    /// Make `child` the first child of `parent` and return `parent`.
+    fn add_child(&mut self, parent: Slot, child: Slot) -> Slot {
+        self.dag[child].next = self.dag[parent].child;
+        self.dag[parent].child = Some(child);
+        parent
+    }
+
+    /// Whether the node at `lhs` belongs above the node at `rhs`. Stable heaps break ties between
+    /// equal keys in favor of the element that was pushed first.
+    fn is_better(&self, lhs: Slot, rhs: Slot) -> bool {
+        let (lhs_key, rhs_key) = (self.dag.key(lhs), self.dag.key(rhs));
+        (self.compare)(lhs_key, rhs_key)
+            || (self.stable
+                && !(self.compare)(rhs_key, lhs_key)
+                && self.dag.sequence(lhs) < self.dag.sequence(rhs))
+    }
+
+    fn link(&mut self, lhs: Slot, rhs: Slot) -> Slot {
+        self.settle(lhs);
+        self.settle(rhs);
+        if self.is_better(lhs, rhs) {
+            self.add_child(lhs, rhs)
+        } else {
+            self.add_child(rhs, lhs)
+        }
+    }
+
+    fn ranked_link(&mut self, lhs: Slot, rhs: Slot) -> Slot {
+        assert!(self.dag[lhs].rank == self.dag[rhs].rank);
+        let parent = self.link(lhs, rhs);
+        self.dag[parent].rank += 1;
+        parent
+    }
+}
+
+impl<K, V> HollowHeap<K, V>
+where
+    K: PartialOrd + Clone + ops::Add<Output = K> + ops::Sub<Output = K>,
+{
+    /// Add `delta` to the keys of all elements in the heap in O(1) time.
+    ///
+    /// The offset is applied lazily: keys are stored relative to it, and `push`, `change_key`,
+    /// `peek_key`, `pop_entry` and `meld` convert between actual and stored keys. The order of
+    /// the heap is unchanged, as adding the same value to all keys does not change their order.
+    /// `HollowHeap::meld` moves the elements of the other heap in O(m) time, with or without
+    /// offsets. Melding heaps with offsets in O(1) time is only available for the heaps of a
+    /// [`HeapForest`](./struct.HeapForest.html), which keep their own offsets, see
+    /// [`HeapForest::add_to_all_keys`](./struct.HeapForest.html#method.add_to_all_keys).
+    ///
+    /// Keys are stored as `key - offset`, which would underflow for keys below the offset of key
+    /// types like unsigned integers. So the offset is a lower bound of the keys that are stored
+    /// after adding to the keys: pushing a smaller key, changing to one or melding one in is
+    /// rejected with `UpdateError::BelowOffset` (or asserted against by the methods that do not
+    /// return errors), for all key types alike.
+    ///
+    /// ```rust
+    /// use hollow_heap::{HollowHeap, UpdateError};
+    ///
+    /// let mut heap: HollowHeap<i64, i64> = HollowHeap::min_heap();
+    /// let handle = heap.push(3);
+    /// heap.add_to_all_keys(5);
+    /// heap.push(6);
+    /// assert_eq!(heap.try_push(4), Err(4));
+    /// assert_eq!(heap.try_change_key(handle, 2), Err(UpdateError::BelowOffset));
+    /// assert_eq!(heap.pop_entry(), Some((6, 6)));
+    /// assert_eq!(heap.pop_entry(), Some((8, 3)));
+    /// ```
+    pub fn add_to_all_keys(&mut self, delta: K) {
+        match &mut self.offset {
+            Some(offset) => offset.value = offset.value.clone() + delta,
+            None => {
+                self.offset = Some(Offset {
+                    value: delta,
+                    add: |lhs, rhs| lhs.clone() + rhs.clone(),
+                    sub: |lhs, rhs| lhs.clone() - rhs.clone(),
+                })
+            }
+        }
     }
 }
 
@@ -437,34 +1371,23 @@ impl<T: PartialOrd + Copy> HollowHeap<T, T> {
     ///
     /// The heap will be able to hold `n` elements without further allocation.
     pub fn with_capacity(n: usize) -> HollowHeap<T, T> {
-        HollowHeap {
-            dag: Arena::with_capacity(n),
-            dag_root: None,
-            compare: min_heap_compare,
-            derive_key: |value| *value,
-        }
+        HollowHeap::with_dag(
+            Dag::with_capacity(n),
+            min_heap_compare,
+            Some(|value| *value),
+        )
     }
 
     /// Create a new empty heap with the chosen compare function.
     pub fn with_compare(compare: fn(&T, &T) -> bool) -> HollowHeap<T, T> {
-        HollowHeap {
-            dag: Arena::new(),
-            dag_root: None,
-            compare,
-            derive_key: |value| *value,
-        }
+        HollowHeap::with_dag(Dag::new(), compare, Some(|value| *value))
     }
 
     /// Create a new empty heap with the chosen compare function and the specified capacity.
     ///
     /// The heap will be able to hold `n` elements without further allocation.
     pub fn with_compare_and_capacity(compare: fn(&T, &T) -> bool, n: usize) -> HollowHeap<T, T> {
-        HollowHeap {
-            dag: Arena::with_capacity(n),
-            dag_root: None,
-            compare,
-            derive_key: |value| *value,
-        }
+        HollowHeap::with_dag(Dag::with_capacity(n), compare, Some(|value| *value))
     }
 
     /// Create a new max heap. (`compare = |lhs, rhs| lhs > rhs`)
@@ -480,7 +1403,9 @@ impl<T: PartialOrd + Copy> HollowHeap<T, T> {
 
 #[cfg(test)]
 mod tests {
-    use super::HollowHeap;
+    use super::{min_heap_compare_nan_last, HollowHeap, HollowHeapBuilder, UpdateError};
+    use alloc::string::String;
+    use alloc::vec::Vec;
 
     #[test]
     fn new_heap_is_empty() {
@@ -521,7 +1446,7 @@ mod tests {
         assert!(heap.pop() == Some(4));
         assert!(heap.pop() == Some(2));
         assert!(heap.pop() == Some(1));
-        assert!(heap.pop() == None);
+        assert!(heap.pop().is_none());
     }
 
     #[test]
@@ -538,46 +1463,330 @@ mod tests {
         assert!(heap.pop() == Some(4));
         assert!(heap.pop() == Some(8));
         assert!(heap.pop() == Some(9));
-        assert!(heap.pop() == None);
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn change_key_with_min_heap() {
+        let mut heap: HollowHeap<u16, u16> = HollowHeap::min_heap();
+        assert!(heap.is_empty());
+        heap.push(5);
+        let index = heap.push(42);
+        heap.push(4);
+        heap.change_key(index, 2);
+        assert!(heap.pop() == Some(42));
+        assert!(heap.pop() == Some(4));
+        assert!(heap.pop() == Some(5));
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn change_item_with_min_heap() {
+        let mut heap: HollowHeap<u16, u16> = HollowHeap::min_heap();
+        assert!(heap.is_empty());
+        heap.push(5);
+        let index = heap.push(42);
+        heap.push(4);
+        heap.change_item(index, 2);
+        assert!(heap.pop() == Some(2));
+        assert!(heap.pop() == Some(4));
+        assert!(heap.pop() == Some(5));
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn faulty_change_key_panics() {
+        let mut heap: HollowHeap<u16, u16> = HollowHeap::min_heap();
+        assert!(heap.is_empty());
+        heap.push(5);
+        let index = heap.push(1);
+        heap.push(4);
+        heap.change_key(index, 2);
+    }
+
+    #[test]
+    fn try_change_key_reports_errors() {
+        let mut heap: HollowHeap<u16, u16> = HollowHeap::min_heap();
+        heap.push(5);
+        let index = heap.push(7);
+        assert!(heap.try_change_key(index, 8) == Err(UpdateError::NotBetter));
+        assert!(heap.try_change_item(index, 7) == Err(UpdateError::NotBetter));
+        let changed = heap.try_change_key(index, 6).unwrap();
+        assert!(heap.try_change_key(index, 1) == Err(UpdateError::Hollow));
+        assert!(heap.pop() == Some(5));
+        assert!(heap.pop() == Some(7));
+        assert!(heap.try_change_key(changed, 1) == Err(UpdateError::EmptyHeap));
+        heap.push(3);
+        assert!(heap.try_change_key(changed, 1) == Err(UpdateError::InvalidHandle));
+    }
+
+    #[test]
+    fn keys_do_not_need_debug() {
+        #[derive(PartialEq, PartialOrd)]
+        struct Key(u8);
+
+        let mut heap: HollowHeap<Key, u8> = HollowHeap::new(|lhs, rhs| lhs < rhs, |val| Key(*val));
+        heap.push(3);
+        let index = heap.push(2);
+        heap.push(4);
+        heap.change_key(index, Key(1));
+        assert!(heap.pop() == Some(2));
+        assert!(heap.pop() == Some(3));
+        assert!(heap.into_iter().next() == Some(4));
+    }
+
+    #[test]
+    fn offsets_apply_to_all_keys() {
+        let mut heap: HollowHeap<i32, char> = HollowHeap::new(|lhs, rhs| lhs < rhs, |_| 0);
+        heap.push_with_key('a', 10);
+        let b = heap.push_with_key('b', 20);
+        heap.add_to_all_keys(-5);
+        heap.push_with_key('c', 12);
+        assert!(heap.peek_key() == Some(5));
+        heap.change_key(b, 4);
+        heap.add_to_all_keys(100);
+        assert!(heap.pop_entry() == Some((104, 'b')));
+        assert!(heap.pop_entry() == Some((105, 'a')));
+        assert!(heap.pop_entry() == Some((112, 'c')));
+        assert!(heap.pop_entry().is_none());
+    }
+
+    #[test]
+    fn offsets_reject_keys_below_the_offset() {
+        let mut heap: HollowHeap<u32, u32> = HollowHeap::min_heap();
+        let handle = heap.push(10);
+        heap.add_to_all_keys(5);
+        assert!(heap.try_push(3) == Err(3));
+        assert!(heap.try_change_key(handle, 4) == Err(UpdateError::BelowOffset));
+        let handle = heap.change_key(handle, 12);
+        assert!(heap.try_update_with(handle, |value| *value = 2) == Err(UpdateError::BelowOffset));
+        let mut other: HollowHeap<u32, u32> = HollowHeap::min_heap();
+        other.push(2);
+        assert!(heap.try_meld(&mut other) == Err(UpdateError::BelowOffset));
+        assert!(other.pop() == Some(2));
+        heap.push(5);
+        assert!(heap.pop_entry() == Some((5, 5)));
+        assert!(heap.pop_entry() == Some((12, 10)));
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn meld_keeps_stable_heaps_in_push_order() {
+        let mut heap = HollowHeapBuilder::new(|job: &(u32, char)| job.0)
+            .stable()
+            .build();
+        heap.push((1, 'x'));
+        heap.push((0, 'y'));
+        let mut other = HollowHeapBuilder::new(|job: &(u32, char)| job.0)
+            .stable()
+            .build();
+        for name in "abcdef".chars() {
+            other.push((1, name));
+        }
+        other.pop();
+        other.push((1, 'g'));
+        heap.meld(other);
+        let popped: String = heap.into_iter().map(|job| job.1).collect();
+        assert!(popped == "yxbcdefg");
+    }
+
+    #[test]
+    fn try_meld_gives_back_heaps_with_rejected_keys() {
+        let mut heap = HollowHeapBuilder::new(|key: &f32| *key)
+            .reject_nan()
+            .build();
+        heap.push(2.0);
+        let mut other: HollowHeap<f32, f32> =
+            HollowHeap::new(min_heap_compare_nan_last, |key| *key);
+        other.push(1.0);
+        other.push(f32::NAN);
+        assert!(heap.try_meld(&mut other) == Err(UpdateError::RejectedKey));
+        assert!(other.pop() == Some(1.0));
+        assert!(other.pop().unwrap().is_nan());
+        other.push(0.5);
+        assert!(heap.try_meld(&mut other).is_ok());
+        assert!(other.is_empty());
+        other.push(3.0);
+        assert!(heap.pop() == Some(0.5));
+        assert!(heap.pop() == Some(2.0));
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn meld_rebases_offsets() {
+        let mut heap: HollowHeap<i32, char> = HollowHeap::new(|lhs, rhs| lhs < rhs, |_| 0);
+        heap.push_with_key('a', 10);
+        heap.add_to_all_keys(3);
+        let mut other: HollowHeap<i32, char> = HollowHeap::new(|lhs, rhs| lhs < rhs, |_| 0);
+        other.push_with_key('b', 1);
+        let c = other.push_with_key('c', 20);
+        other.change_key(c, 11);
+        other.add_to_all_keys(2);
+        heap.meld(other);
+        let popped: Vec<_> = (0..3).filter_map(|_| heap.pop_entry()).collect();
+        assert!(
+            popped == [(3, 'b'), (13, 'a'), (13, 'c')]
+                || popped == [(3, 'b'), (13, 'c'), (13, 'a')]
+        );
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn change_keys_validates_the_whole_batch() {
+        let mut heap: HollowHeap<u32, u32> = HollowHeap::min_heap();
+        let handles: Vec<_> = (1..=6).map(|value| heap.push(value * 10)).collect();
+        let nodes = heap.dag.len();
+        let duplicate = [(handles[2], 5), (handles[2], 4)];
+        assert!(heap.change_keys(duplicate.iter().copied()) == Err(UpdateError::DuplicateHandle));
+        let not_better = [(handles[1], 5), (handles[3], 45)];
+        assert!(heap.change_keys(not_better.iter().copied()) == Err(UpdateError::NotBetter));
+        assert!(heap.dag.len() == nodes);
+        let changes = [(handles[5], 1), (handles[0], 2), (handles[4], 3)];
+        let new_handles = heap.change_keys(changes.iter().copied()).unwrap();
+        assert!(new_handles.len() == 3);
+        // the root is changed in place
+        assert!(new_handles[1] == handles[0]);
+        assert!(heap.change_key(new_handles[2], 0) != new_handles[2]);
+        let popped: Vec<_> = heap.into_iter().collect();
+        assert!(popped == [50, 60, 10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn update_with_moves_elements_both_ways() {
+        let mut heap: HollowHeap<u32, (u32, char)> =
+            HollowHeap::new(|lhs, rhs| lhs < rhs, |job| job.0);
+        let handles: Vec<_> = "abcdef"
+            .chars()
+            .zip(1..)
+            .map(|(name, priority)| heap.push((priority * 10, name)))
+            .collect();
+        heap.pop();
+        let b = heap.update_with(handles[1], |job| job.0 = 70).unwrap();
+        let e = heap.update_with(handles[4], |job| job.0 = 5).unwrap();
+        assert!(e != handles[4]);
+        let hollow = heap.update_with(handles[4], |job| job.0 = 1);
+        assert!(hollow == Err((UpdateError::Hollow, None)));
+        let invalid = heap.update_with(handles[0], |job| job.0 = 1);
+        assert!(invalid == Err((UpdateError::InvalidHandle, None)));
+        assert!(heap.update_with(handles[2], |job| job.1 = 'C') == Ok(handles[2]));
+        // worsening the root
+        assert!(heap.peek() == Some(&(5, 'e')));
+        heap.update_with(e, |job| job.0 = 45).unwrap();
+        assert!(heap.update_with(b, |job| job.0 = 40).unwrap() != b);
+        let popped: Vec<_> = heap.into_iter().collect();
+        assert!(
+            popped == [(30, 'C'), (40, 'b'), (40, 'd'), (45, 'e'), (60, 'f')]
+                || popped == [(30, 'C'), (40, 'd'), (40, 'b'), (45, 'e'), (60, 'f')]
+        );
+    }
+
+    #[test]
+    fn update_with_does_not_need_clone() {
+        struct Job {
+            priority: u32,
+        }
+
+        let mut heap: HollowHeap<u32, Job> =
+            HollowHeap::new(|lhs, rhs| lhs < rhs, |job| job.priority);
+        heap.push(Job { priority: 2 });
+        let handle = heap.push(Job { priority: 3 });
+        assert!(heap.update_with(handle, |job| job.priority = 1).is_ok());
+        assert!(heap.pop().map(|job| job.priority) == Some(1));
+        assert!(heap.pop().map(|job| job.priority) == Some(2));
     }
 
     #[test]
-    fn change_key_with_min_heap() {
-        let mut heap: HollowHeap<u16, u16> = HollowHeap::min_heap();
-        assert!(heap.is_empty());
-        heap.push(5);
-        let index = heap.push(42);
-        heap.push(4);
-        heap.change_key(index, 2);
-        assert!(heap.pop() == Some(42));
-        assert!(heap.pop() == Some(4));
-        assert!(heap.pop() == Some(5));
-        assert!(heap.pop() == None);
+    fn set_compare_rebuilds_the_heap() {
+        let mut heap: HollowHeap<u32, (u32, u32)> =
+            HollowHeap::new(|lhs, rhs| lhs < rhs, |job| job.0);
+        let handles: Vec<_> = (0..10).map(|age| heap.push((age, age % 4))).collect();
+        let handle = heap.change_key(handles[7], 0);
+        heap.delete(handles[3]);
+        heap.set_compare(|lhs, rhs| lhs > rhs);
+        assert!(heap.dag.hollow_len() == 0);
+        assert!(heap.peek() == Some(&(9, 1)));
+        heap.change_key(handle, 20);
+        assert!(heap.peek() == Some(&(7, 3)));
+        assert!(heap.set_derive_key(|job| job.1).is_ok());
+        assert!(heap.derive_key()(&(1, 2)) == 2);
+        heap.change_key(handles[4], 7);
+        let popped: Vec<_> = heap.into_iter().collect();
+        assert!(popped[..2] == [(4, 0), (7, 3)]);
+        assert!(popped.windows(2).skip(1).all(|pair| pair[0].1 >= pair[1].1));
+        assert!(popped.len() == 9);
     }
 
     #[test]
-    fn change_item_with_min_heap() {
-        let mut heap: HollowHeap<u16, u16> = HollowHeap::min_heap();
-        assert!(heap.is_empty());
-        heap.push(5);
-        let index = heap.push(42);
-        heap.push(4);
-        heap.change_item(index, 2);
-        assert!(heap.pop() == Some(2));
-        assert!(heap.pop() == Some(4));
-        assert!(heap.pop() == Some(5));
-        assert!(heap.pop() == None);
+    fn map_converts_items_and_keys() {
+        let mut heap: HollowHeap<i32, i32> = HollowHeap::min_heap();
+        let handles: Vec<_> = [5, 3, 9, 3].iter().map(|cost| heap.push(*cost)).collect();
+        let handle = heap.change_key(handles[2], 1);
+        heap.delete(handles[0]);
+        heap.add_to_all_keys(10);
+        let (mut ties, moved) = heap.map_keys(
+            |lhs: &(i32, u64), rhs| lhs < rhs,
+            |cost| (*cost, 0),
+            |cost| (cost, 100 - cost as u64),
+        );
+        assert!(moved.len() == 3);
+        assert!(moved.get(handles[0]).is_none());
+        assert!(ties.peek_key() == Some((11, 89)));
+        let three = moved.get(handles[3]).unwrap();
+        ties.change_key(three, (13, 1));
+        let (mut names, renamed) = ties.map(
+            |lhs: &usize, rhs| lhs > rhs,
+            |name: &String| name.len(),
+            |cost| "x".repeat(cost as usize),
+        );
+        let nine = renamed.get(moved.get(handle).unwrap()).unwrap();
+        names.change_key(nine, 20);
+        let popped: Vec<_> = names.into_iter().map(|name| name.len()).collect();
+        assert!(popped == [9, 3, 3]);
     }
 
     #[test]
-    #[should_panic]
-    fn faulty_change_key_panics() {
-        let mut heap: HollowHeap<u16, u16> = HollowHeap::min_heap();
+    fn split_top_moves_the_best_elements() {
+        let mut heap: HollowHeap<u32, u32> = HollowHeap::min_heap();
+        let handles: Vec<_> = (0..10).map(|value| heap.push(value * 10)).collect();
+        let changed = heap.change_key(handles[9], 15);
+        let (mut top, moved) = heap.split_top(3);
+        assert!(moved.len() == 3);
+        assert!(moved.get(handles[5]).is_none());
+        let new_changed = moved.get(changed).expect("The changed element was moved.");
+        top.change_key(new_changed, 5);
+        assert!(top.pop() == Some(0));
+        assert!(top.pop() == Some(90));
+        assert!(top.pop() == Some(10));
+        assert!(top.is_empty());
+        heap.change_key(handles[5], 1);
+        assert!(heap.pop() == Some(50));
+        assert!(heap.pop() == Some(20));
+        let (rest, moved) = heap.split_top(10);
+        assert!(moved.len() == 5);
         assert!(heap.is_empty());
-        heap.push(5);
-        let index = heap.push(1);
-        heap.push(4);
-        heap.change_key(index, 2);
+        assert!(rest.into_iter().eq([30, 40, 60, 70, 80].iter().copied()));
+    }
+
+    #[test]
+    fn split_off_moves_matching_keys() {
+        let mut heap: HollowHeap<i32, i32> = HollowHeap::new(|lhs, rhs| lhs < rhs, |value| *value);
+        let handles: Vec<_> = (0..10).map(|value| heap.push(value)).collect();
+        heap.change_key(handles[7], -7);
+        heap.add_to_all_keys(100);
+        let (mut odd, moved) = heap.split_off(|key| key % 2 != 0);
+        // the changed key of 7 is odd
+        assert!(moved.len() == 5);
+        assert!(moved.get(handles[7]).is_none());
+        for handle in [1, 3, 5, 9].iter().map(|index| handles[*index]) {
+            assert!(moved.get(handle).is_some());
+        }
+        odd.change_key(moved.get(handles[9]).unwrap(), 0);
+        heap.change_key(handles[4], 101);
+        let popped: Vec<_> = (0..5).filter_map(|_| heap.pop_entry()).collect();
+        assert!(popped == [(100, 0), (101, 4), (102, 2), (106, 6), (108, 8)]);
+        let popped: Vec<_> = (0..5).filter_map(|_| odd.pop_entry()).collect();
+        assert!(popped == [(0, 9), (93, 7), (101, 1), (103, 3), (105, 5)]);
     }
 
     #[test]
@@ -592,7 +1801,25 @@ mod tests {
         assert!(heap.pop() == Some(2));
         assert!(heap.pop() == Some(2));
         assert!(heap.pop() == Some(1));
-        assert!(heap.pop() == None);
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn clear_keeps_capacity_and_compare() {
+        let mut heap: HollowHeap<u8, u8> = HollowHeap::with_compare_and_capacity(|l, r| l > r, 8);
+        heap.push(2);
+        let index = heap.push(5);
+        heap.push(3);
+        heap.change_key(index, 7);
+        heap.clear();
+        assert!(heap.is_empty());
+        assert!(heap.peek().is_none());
+        assert!(heap.dag.capacity() == 8);
+        heap.push(1);
+        heap.push(4);
+        assert!(heap.pop() == Some(4));
+        assert!(heap.pop() == Some(1));
+        assert!(heap.pop().is_none());
     }
 
     #[derive(PartialEq, Eq)]
@@ -616,7 +1843,7 @@ mod tests {
         assert!(heap.pop() == Some(&second));
         assert!(heap.pop() == Some(&first));
         assert!(heap.pop() == Some(&third));
-        assert!(heap.pop() == None);
+        assert!(heap.pop().is_none());
     }
 
     #[test]
@@ -636,16 +1863,41 @@ mod tests {
         assert!(heap.pop() == Some(&third));
         assert!(heap.pop() == Some(&changed));
         assert!(heap.pop() == Some(&second));
-        assert!(heap.pop() == None);
+        assert!(heap.pop().is_none());
     }
 
+    #[test]
+    fn compact_drops_hollow_nodes_and_keeps_handles() {
+        let mut heap: HollowHeap<u32, u32> = HollowHeap::min_heap();
+        heap.push(1);
+        let deleted = heap.push(7);
+        let changed = heap.push(9);
+        let kept = heap.push(8);
+        heap.delete(deleted);
+        let changed = heap.change_key(changed, 5);
+        assert!(heap.dag.hollow_len() == 2);
+        heap.compact();
+        assert!(heap.dag.hollow_len() == 0);
+        assert!(heap.dag.len() == 3);
+        heap.change_key(kept, 2);
+        heap.change_key(changed, 3);
+        assert!(heap.pop() == Some(1));
+        assert!(heap.pop() == Some(8));
+        assert!(heap.pop() == Some(9));
+        assert!(heap.pop().is_none());
+    }
 }
 
 /// A builder to construct a [`HollowHeap`](./struct.HollowHeap.html).
-/// Allows specifying key derivation and compare functions as well as capacity.
+/// Allows specifying key derivation and compare functions, capacity and when to rebuild.
 #[derive(Clone)]
 pub struct HollowHeapBuilder<K, V> {
     capacity: Option<usize>,
+    rebuild_ratio: Option<f32>,
+    one_parent: bool,
+    lazy_linking: bool,
+    stable: bool,
+    validate_key: Option<fn(&K) -> bool>,
     compare: fn(&K, &K) -> bool,
     derive_key: fn(&V) -> K,
 }
@@ -658,6 +1910,11 @@ impl<K: PartialOrd, V> HollowHeapBuilder<K, V> {
     pub fn new(derive_key: fn(&V) -> K) -> HollowHeapBuilder<K, V> {
         HollowHeapBuilder {
             capacity: None,
+            rebuild_ratio: Some(DEFAULT_REBUILD_RATIO),
+            one_parent: false,
+            lazy_linking: false,
+            stable: false,
+            validate_key: None,
             compare: min_heap_compare,
             derive_key,
         }
@@ -670,6 +1927,67 @@ impl<K: PartialOrd, V> HollowHeapBuilder<K, V> {
         self
     }
 
+    /// Rebuild the heap once there are more than `ratio` hollow nodes per element.
+    ///
+    /// `change_key`, `change_item` and `delete` of anything but the top-most element leave hollow
+    /// nodes behind, which are only freed once `pop` happens to reach them. Rebuilding bounds the
+    /// memory used by hollow nodes in workloads that change many keys between pops. Defaults to
+    /// `2.0`.
+    ///
+    /// Asserts that `ratio` is finite and positive: a ratio of `0.0` would rebuild the whole heap
+    /// for every hollow node, and `NaN` would never rebuild. Use `without_rebuild` to disable
+    /// rebuilds.
+    pub fn with_rebuild_ratio(&mut self, ratio: f32) -> &mut HollowHeapBuilder<K, V> {
+        assert!(
+            ratio.is_finite() && ratio > 0.0,
+            "Should only be rebuilding at a finite and positive ratio."
+        );
+        self.rebuild_ratio = Some(ratio);
+        self
+    }
+
+    /// Never rebuild the heap automatically.
+    /// [`HollowHeap::compact`](./struct.HollowHeap.html#method.compact) can still be used to drop
+    /// hollow nodes manually.
+    pub fn without_rebuild(&mut self) -> &mut HollowHeapBuilder<K, V> {
+        self.rebuild_ratio = None;
+        self
+    }
+
+    /// Use the one-parent variant of the hollow heap instead of the default two-parent one.
+    ///
+    /// When the key of a non-root element is changed, the new node takes over all but the first
+    /// two children of the node that turns hollow instead of adopting it as a second parent. Both
+    /// variants have the same amortized bounds but different constant factors, see section 6 of
+    /// the [hollow heap paper](https://arxiv.org/abs/1510.06535).
+    pub fn one_parent(&mut self) -> &mut HollowHeapBuilder<K, V> {
+        self.one_parent = true;
+        self
+    }
+
+    /// Keep a list of roots and only link them when the heap is changed other than by pushing,
+    /// e.g. when the top-most element is removed, instead of linking every pushed element with
+    /// the root right away.
+    ///
+    /// Pushing compares the new element with the top-most one to keep track of it, and leaves the
+    /// nodes already in the heap untouched. The roots are linked by rank once they have to be,
+    /// like the children of a deleted root, which takes about as many comparisons as linking
+    /// them eagerly. Elements with different keys are popped in the same order as without lazy
+    /// linking, and elements with equal keys as well if the heap is `stable`.
+    pub fn lazy_linking(&mut self, lazy: bool) -> &mut HollowHeapBuilder<K, V> {
+        self.lazy_linking = lazy;
+        self
+    }
+
+    /// Pop elements with equal keys in the order they were pushed (first in, first out).
+    ///
+    /// Stores a sequence number per element. Changing the key or item of an element keeps its
+    /// original place among elements with the new key.
+    pub fn stable(&mut self) -> &mut HollowHeapBuilder<K, V> {
+        self.stable = true;
+        self
+    }
+
     /// Specify the compare function to use.
     pub fn with_compare(&mut self, compare: fn(&K, &K) -> bool) -> &mut HollowHeapBuilder<K, V> {
         self.compare = compare;
@@ -690,21 +2008,33 @@ impl<K: PartialOrd, V> HollowHeapBuilder<K, V> {
 
     /// Finish constructing the HollowHeap and return it.
     pub fn build(&self) -> HollowHeap<K, V> {
-        if let Some(capacity) = self.capacity {
-            HollowHeap {
-                dag: Arena::with_capacity(capacity),
-                dag_root: None,
-                compare: self.compare,
-                derive_key: self.derive_key,
-            }
+        let mut heap = if let Some(capacity) = self.capacity {
+            HollowHeap::with_dag(
+                Dag::with_capacity(capacity),
+                self.compare,
+                Some(self.derive_key),
+            )
         } else {
-            HollowHeap {
-                dag: Arena::new(),
-                dag_root: None,
-                compare: self.compare,
-                derive_key: self.derive_key,
-            }
-        }
+            HollowHeap::with_dag(Dag::new(), self.compare, Some(self.derive_key))
+        };
+        heap.rebuild_ratio = self.rebuild_ratio;
+        heap.one_parent = self.one_parent;
+        heap.lazy_linking = self.lazy_linking;
+        heap.stable = self.stable;
+        heap.validate_key = self.validate_key;
+        heap
+    }
+}
+
+impl<K: Float, V> HollowHeapBuilder<K, V> {
+    /// Reject `NaN` keys: `try_push` gives back the value instead of pushing it, while `push`,
+    /// `change_key` and `change_item` panic.
+    ///
+    /// Alternatively, use a compare function that orders `NaN` like
+    /// [`min_heap_compare_nan_last`](./fn.min_heap_compare_nan_last.html).
+    pub fn reject_nan(&mut self) -> &mut HollowHeapBuilder<K, V> {
+        self.validate_key = Some(float::is_not_nan);
+        self
     }
 }
 
@@ -712,6 +2042,11 @@ impl<T: PartialOrd + Copy> HollowHeapBuilder<T, T> {
     pub fn new_with_value_is_key() -> HollowHeapBuilder<T, T> {
         HollowHeapBuilder {
             capacity: None,
+            rebuild_ratio: Some(DEFAULT_REBUILD_RATIO),
+            one_parent: false,
+            lazy_linking: false,
+            stable: false,
+            validate_key: None,
             compare: min_heap_compare,
             derive_key: |value| *value,
         }
@@ -720,7 +2055,8 @@ impl<T: PartialOrd + Copy> HollowHeapBuilder<T, T> {
 
 #[cfg(test)]
 mod builder_tests {
-    use super::HollowHeapBuilder;
+    use super::{HollowHeap, HollowHeapBuilder, UpdateError};
+    use alloc::vec::Vec;
 
     #[derive(PartialEq, Eq)]
     struct St {
@@ -743,11 +2079,269 @@ mod builder_tests {
         assert!(heap.pop() == Some(St { val: 30 }));
         assert!(heap.pop() == Some(St { val: 40 }));
         assert!(heap.pop() == Some(St { val: 50 }));
-        assert!(heap.pop() == None);
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn rebuild_ratio_bounds_hollow_nodes() {
+        let mut heap = HollowHeapBuilder::new_with_value_is_key()
+            .with_rebuild_ratio(1.0)
+            .build();
+        heap.push(0u32);
+        let mut handles: Vec<_> = (1..=10u32).map(|i| heap.push(i * 100)).collect();
+        for round in 1..50 {
+            for handle in handles.iter_mut() {
+                let key = heap.dag.key(heap.dag.slot(*handle).unwrap()) - 1;
+                *handle = heap.change_key(*handle, key);
+                assert!(
+                    heap.dag.hollow_len() <= heap.dag.full_len(),
+                    "round {}",
+                    round
+                );
+            }
+        }
+        assert!(heap.pop() == Some(0));
+        for i in 1..=10u32 {
+            assert!(heap.pop() == Some(i * 100));
+        }
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn len_counts_only_elements() {
+        let mut heap = HollowHeapBuilder::new_with_value_is_key()
+            .without_rebuild()
+            .build();
+        heap.push(1u32);
+        let handle = heap.push(5);
+        let handle = heap.change_key(handle, 4);
+        heap.delete(handle);
+        assert!(heap.dag.len() == 3);
+        assert!(heap.len() == 1);
+        heap.pop();
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rebuild_ratio_has_to_be_positive() {
+        HollowHeapBuilder::<u32, u32>::new_with_value_is_key().with_rebuild_ratio(0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rebuild_ratio_has_to_be_a_number() {
+        HollowHeapBuilder::<u32, u32>::new_with_value_is_key().with_rebuild_ratio(f32::NAN);
+    }
+
+    #[test]
+    fn one_parent_never_sets_second_parent() {
+        let mut heap = HollowHeapBuilder::new_with_value_is_key()
+            .one_parent()
+            .without_rebuild()
+            .build();
+        let handles: Vec<_> = (0..32u32).map(|i| heap.push(i + 100)).collect();
+        heap.push(0);
+        assert!(heap.pop() == Some(0));
+        for (i, handle) in handles.into_iter().enumerate().skip(1) {
+            heap.change_item(handle, 50 - i as u32);
+        }
+        assert!(heap.dag.hollow_len() > 0);
+        for slot in heap.nodes() {
+            assert!(heap.dag[slot].second_parent.is_none());
+        }
+        assert!(heap.pop() == Some(19));
+        assert!(heap.pop() == Some(20));
+    }
+
+    #[test]
+    fn lazy_linking_only_compares_with_the_root_on_push() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static COMPARISONS: AtomicUsize = AtomicUsize::new(0);
+        fn counting_min(lhs: &u32, rhs: &u32) -> bool {
+            COMPARISONS.fetch_add(1, Ordering::Relaxed);
+            lhs < rhs
+        }
+        let mut counts = Vec::new();
+        for lazy in [false, true].iter() {
+            let mut heap = HollowHeapBuilder::new_with_value_is_key()
+                .with_compare(counting_min)
+                .lazy_linking(*lazy)
+                .build();
+            COMPARISONS.store(0, Ordering::Relaxed);
+            for i in 0..10_000u32 {
+                heap.push(i.wrapping_mul(2_654_435_761) % 100_000);
+            }
+            let pushed = COMPARISONS.load(Ordering::Relaxed);
+            for _ in 0..10 {
+                heap.pop();
+            }
+            counts.push((pushed, COMPARISONS.load(Ordering::Relaxed)));
+        }
+        assert!(counts[1].0 == 9_999);
+        assert!(counts[1].1 <= counts[0].1);
+    }
+
+    #[test]
+    fn lazy_linking_links_roots_on_pop() {
+        let mut heap = HollowHeapBuilder::new_with_value_is_key()
+            .lazy_linking(true)
+            .max_heap()
+            .build();
+        let handles: Vec<_> = [3u32, 8, 5, 1].iter().map(|i| heap.push(*i)).collect();
+        assert!(handles
+            .iter()
+            .all(|handle| heap.dag[heap.dag.slot(*handle).unwrap()].child.is_none()));
+        assert!(heap.peek() == Some(&8));
+        heap.change_item(handles[0], 9);
+        assert!(heap.peek() == Some(&9));
+        assert!(heap.pop() == Some(9));
+        assert!(heap.pop() == Some(8));
+        heap.push(7);
+        assert!(heap.pop() == Some(7));
+        assert!(heap.pop() == Some(5));
+        assert!(heap.pop() == Some(1));
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn stable_heap_pops_equal_keys_in_push_order() {
+        let mut heap = HollowHeapBuilder::new(|job: &(u8, char)| job.0)
+            .stable()
+            .build();
+        heap.push((2, 'a'));
+        let b = heap.push((3, 'b'));
+        heap.push((1, 'c'));
+        heap.push((2, 'd'));
+        heap.push((1, 'e'));
+        heap.push((2, 'f'));
+        heap.change_item(b, (2, 'b'));
+        let popped: Vec<_> = heap.into_iter().map(|job| job.1).collect();
+        assert!(popped == ['c', 'e', 'a', 'b', 'd', 'f']);
+    }
+
+    #[test]
+    fn reject_nan_gives_back_the_value() {
+        let mut heap = HollowHeapBuilder::new(|val: &(f32, u8)| val.0)
+            .reject_nan()
+            .build();
+        assert!(heap.try_push((1.5, 1)).is_ok());
+        match heap.try_push((f32::NAN, 2)) {
+            Err((key, id)) => assert!(key.is_nan() && id == 2),
+            Ok(_) => panic!("NaN keys should be rejected."),
+        }
+        assert!(heap.try_push((0.5, 3)).is_ok());
+        assert!(heap.pop() == Some((0.5, 3)));
+        assert!(heap.pop() == Some((1.5, 1)));
+        assert!(heap.pop().is_none());
+    }
+
+    #[test]
+    fn update_with_removes_the_element_if_its_key_is_rejected() {
+        let mut heap = HollowHeapBuilder::new(|val: &(f32, u8)| val.0)
+            .reject_nan()
+            .build();
+        heap.push((1.0, 1));
+        let handle = heap.push((2.0, 2));
+        heap.push((3.0, 3));
+        let rejected = heap.update_with(handle, |val| val.0 = f32::NAN);
+        assert!(
+            matches!(rejected, Err((UpdateError::RejectedKey, Some((key, 2)))) if key.is_nan())
+        );
+        assert!(heap.len() == 2);
+        assert!(heap.update_with(handle, |val| val.0 = 0.5).is_err());
+        assert!(heap.pop() == Some((1.0, 1)));
+        assert!(heap.pop() == Some((3.0, 3)));
+    }
+
+    #[test]
+    fn try_update_with_keeps_the_item_if_the_key_is_rejected() {
+        let mut heap = HollowHeapBuilder::new(|val: &(f32, u8)| val.0)
+            .reject_nan()
+            .build();
+        heap.push((1.0, 1));
+        let handle = heap.push((2.0, 2));
+        let rejected = heap.try_update_with(handle, |val| *val = (f32::NAN, 3));
+        assert!(rejected == Err(UpdateError::RejectedKey));
+        assert!(heap.try_update_with(handle, |val| val.0 = 0.5).is_ok());
+        assert!(heap.pop() == Some((0.5, 2)));
+        assert!(heap.pop() == Some((1.0, 1)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn reject_nan_panics_on_push() {
+        let mut heap = HollowHeapBuilder::new(|val: &f64| *val)
+            .reject_nan()
+            .build();
+        heap.push(f64::NAN);
+    }
+
+    #[test]
+    fn rejected_derive_key_is_not_installed() {
+        let mut heap = HollowHeapBuilder::new(|val: &(f32, f32)| val.0)
+            .reject_nan()
+            .build();
+        heap.push((2.0, f32::NAN));
+        heap.push((1.0, 3.0));
+        assert!(heap.set_derive_key(|val| val.1) == Err(UpdateError::RejectedKey));
+        assert!(heap.rekey_all().is_ok());
+        assert!(heap.derive_key()(&(4.0, 5.0)) == 4.0);
+        assert!(heap.pop() == Some((1.0, 3.0)));
+    }
+
+    #[test]
+    fn without_rebuild_keeps_hollow_nodes() {
+        let mut heap = HollowHeapBuilder::new_with_value_is_key()
+            .without_rebuild()
+            .build();
+        heap.push(0u32);
+        let handle = heap.push(10);
+        let handle = heap.change_key(handle, 9);
+        heap.change_key(handle, 8);
+        assert!(heap.dag.hollow_len() == 2);
+        heap.compact();
+        assert!(heap.dag.hollow_len() == 0);
+        assert!(heap.dag.len() == 2);
+        assert!(heap.pop() == Some(0));
+        assert!(heap.pop() == Some(10));
+    }
+
+    /// Leave hollow nodes with one and two parents behind after the heap shrank.
+    fn churn(heap: &mut HollowHeap<u32, u32>) {
+        for round in 0..4u32 {
+            let handles: Vec<_> = (0..64u32)
+                .map(|i| heap.push(1_000 + round * 100 + i))
+                .collect();
+            for _ in 0..16 {
+                heap.pop();
+            }
+            for (i, handle) in handles.into_iter().enumerate().skip(16).step_by(3) {
+                heap.change_key(handle, 950 + round * 100 + i as u32);
+            }
+        }
+    }
+
+    #[test]
+    fn compact_walks_every_node_once() {
+        for (one_parent, lazy) in [(false, false), (true, false), (false, true)].iter() {
+            let mut builder = HollowHeapBuilder::new_with_value_is_key();
+            builder.without_rebuild().lazy_linking(*lazy);
+            if *one_parent {
+                builder.one_parent();
+            }
+            let mut heap = builder.build();
+            churn(&mut heap);
+            assert!(heap.dag.hollow_len() > 0);
+            assert!(heap.nodes().len() == heap.dag.len());
+            heap.compact();
+            assert!(heap.dag.len() == 192);
+            assert!(heap.nodes().len() == 192);
+        }
     }
 }
 
-impl<K: PartialOrd + fmt::Debug, V> IntoIterator for HollowHeap<K, V> {
+impl<K: PartialOrd, V> IntoIterator for HollowHeap<K, V> {
     type Item = V;
     type IntoIter = IntoIter<K, V>;
     fn into_iter(self) -> Self::IntoIter {
@@ -760,7 +2354,7 @@ pub struct IntoIter<K, V> {
     inner: HollowHeap<K, V>,
 }
 
-impl<K: PartialOrd + fmt::Debug, V> Iterator for IntoIter<K, V> {
+impl<K: PartialOrd, V> Iterator for IntoIter<K, V> {
     type Item = V;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -768,14 +2362,218 @@ impl<K: PartialOrd + fmt::Debug, V> Iterator for IntoIter<K, V> {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = self.inner.dag.len();
+        let len = self.inner.dag.full_len();
+        (len, Some(len))
+    }
+}
+
+/// An iterator over the values of a heap from best to worst that leaves the heap untouched, see
+/// [`HollowHeap::iter_sorted`](./struct.HollowHeap.html#method.iter_sorted).
+pub struct SortedIter<'a, K, V> {
+    heap: &'a HollowHeap<K, V>,
+    /// The full nodes whose parents were yielded, as a binary heap ordered by `is_better`.
+    frontier: Vec<Slot>,
+    /// Scratch space for expanding hollow nodes, which are skipped.
+    hollow: Vec<Slot>,
+    /// The number of values not yet yielded.
+    remaining: usize,
+}
+
+impl<'a, K: PartialOrd, V> SortedIter<'a, K, V> {
+    fn new(heap: &'a HollowHeap<K, V>) -> SortedIter<'a, K, V> {
+        let mut iter = SortedIter {
+            heap,
+            frontier: Vec::new(),
+            hollow: Vec::new(),
+            remaining: heap.dag.full_len(),
+        };
+        if heap.lazy_linking {
+            // the listed roots are all full, as the roots are linked before an element other
+            // than the best root is removed, and removing the best root links them all
+            let mut next_root = heap.root_list;
+            while let Some(root) = next_root {
+                next_root = heap.dag[root].next;
+                iter.visit(root);
+            }
+        } else if let Some(root) = heap.dag_root {
+            iter.visit(root);
+        }
+        iter.expand_hollow();
+        iter
+    }
+
+    /// Add the node at `slot` to the frontier if it is full, or queue its children otherwise.
+    fn visit(&mut self, slot: Slot) {
+        if self.heap.dag.is_hollow(slot) {
+            self.hollow.push(slot);
+        } else {
+            self.push_frontier(slot);
+        }
+    }
+
+    /// Visit the children of `parent`. A hollow node with two parents is only visited through its
+    /// first parent, where it is not the last child (see `delete_node`), so it is visited once.
+    fn visit_children(&mut self, parent: Slot) {
+        let dag = &self.heap.dag;
+        let mut next_child = dag[parent].child;
+        while let Some(child) = next_child {
+            if dag[child].second_parent == Some(parent) {
+                break;
+            }
+            next_child = dag[child].next;
+            self.visit(child);
+        }
+    }
+
+    fn expand_hollow(&mut self) {
+        while let Some(hollow) = self.hollow.pop() {
+            self.visit_children(hollow);
+        }
+    }
+
+    fn push_frontier(&mut self, slot: Slot) {
+        let mut position = self.frontier.len();
+        self.frontier.push(slot);
+        while position > 0 {
+            let parent = (position - 1) / 2;
+            if !self
+                .heap
+                .is_better(self.frontier[position], self.frontier[parent])
+            {
+                break;
+            }
+            self.frontier.swap(position, parent);
+            position = parent;
+        }
+    }
+
+    fn pop_frontier(&mut self) -> Option<Slot> {
+        let last = self.frontier.len().checked_sub(1)?;
+        self.frontier.swap(0, last);
+        let best = self.frontier.pop();
+        let mut position = 0;
+        loop {
+            let mut better = position;
+            for child in [2 * position + 1, 2 * position + 2].iter().copied() {
+                if child < self.frontier.len()
+                    && self
+                        .heap
+                        .is_better(self.frontier[child], self.frontier[better])
+                {
+                    better = child;
+                }
+            }
+            if better == position {
+                return best;
+            }
+            self.frontier.swap(position, better);
+            position = better;
+        }
+    }
+}
+
+impl<'a, K: PartialOrd, V> Iterator for SortedIter<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let best = self.pop_frontier()?;
+        self.visit_children(best);
+        self.expand_hollow();
+        self.remaining -= 1;
+        self.heap.dag.item(best)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K: PartialOrd, V> ExactSizeIterator for SortedIter<'a, K, V> {}
+
+/// An iterator popping values while a predicate holds, see
+/// [`HollowHeap::pop_while`](./struct.HollowHeap.html#method.pop_while).
+pub struct PopWhile<'a, K, V, F> {
+    heap: &'a mut HollowHeap<K, V>,
+    predicate: F,
+    /// Set once the predicate failed, so that it is not called again.
+    done: bool,
+}
+
+impl<'a, K: PartialOrd, V, F: FnMut(&K, &V) -> bool> Iterator for PopWhile<'a, K, V, F> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || !self.heap.top_satisfies(&mut self.predicate) {
+            self.done = true;
+            return None;
+        }
+        self.heap.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = if self.done {
+            0
+        } else {
+            self.heap.dag.full_len()
+        };
+        (0, Some(len))
+    }
+}
+
+/// An iterator popping up to a number of values, see
+/// [`HollowHeap::pop_n`](./struct.HollowHeap.html#method.pop_n).
+pub struct PopN<'a, K, V> {
+    heap: &'a mut HollowHeap<K, V>,
+    /// The number of values still to pop.
+    n: usize,
+}
+
+impl<'a, K: PartialOrd, V> Iterator for PopN<'a, K, V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        self.heap.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = cmp::min(self.n, self.heap.dag.full_len());
         (len, Some(len))
     }
 }
 
+impl<'a, K: PartialOrd, V> ExactSizeIterator for PopN<'a, K, V> {}
+
+/// An iterator popping values up to a key, see
+/// [`HollowHeap::pop_until`](./struct.HollowHeap.html#method.pop_until).
+pub struct PopUntil<'a, K, V> {
+    heap: &'a mut HollowHeap<K, V>,
+    key: K,
+}
+
+impl<'a, K: PartialOrd, V> Iterator for PopUntil<'a, K, V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (compare, until) = (self.heap.compare, &self.key);
+        if !self.heap.top_satisfies(|key, _| !compare(until, key)) {
+            return None;
+        }
+        self.heap.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.heap.dag.full_len()))
+    }
+}
+
 #[cfg(test)]
 mod iter_tests {
     use super::HollowHeap;
+    use alloc::vec::Vec;
 
     #[test]
     fn iterator_returns_sorted_items() {
@@ -792,6 +2590,52 @@ mod iter_tests {
         assert!(iter.next() == Some(4));
         assert!(iter.next() == Some(2));
         assert!(iter.next() == Some(1));
-        assert!(iter.next() == None);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn iter_sorted_leaves_the_heap_untouched() {
+        let mut heap: HollowHeap<u32, u32> = HollowHeap::min_heap();
+        let handles: Vec<_> = (1..=8).map(|value| heap.push(value * 10)).collect();
+        heap.pop();
+        heap.change_key(handles[6], 5);
+        heap.delete(handles[3]);
+        let nodes = heap.dag.len();
+        let sorted: Vec<_> = heap.iter_sorted().copied().collect();
+        assert!(sorted == [70, 20, 30, 50, 60, 80]);
+        assert!(heap.iter_sorted().len() == 6);
+        assert!(heap.nth_best(0) == heap.peek());
+        assert!(heap.nth_best(3) == Some(&50));
+        assert!(heap.nth_best(6).is_none());
+        assert!(heap.dag.len() == nodes);
+        assert!(heap.into_iter().eq(sorted.into_iter()));
+    }
+
+    #[test]
+    fn pop_iterators_stop_early() {
+        let mut heap: HollowHeap<i32, i32> = HollowHeap::min_heap();
+        for value in (0..20).rev() {
+            heap.push(value * 5);
+        }
+        heap.add_to_all_keys(1);
+        assert!(heap.pop_until(16).eq([0, 5, 10, 15].iter().copied()));
+        assert!(heap.pop_n(2).eq([20, 25].iter().copied()));
+        let mut calls = 0;
+        let mut due = heap.pop_while(|key, value| {
+            calls += 1;
+            *key == value + 1 && *key < 40
+        });
+        assert!(due.next() == Some(30));
+        assert!(due.next() == Some(35));
+        assert!(due.next().is_none());
+        assert!(due.next().is_none());
+        assert!(calls == 3);
+        let mut batch = heap.pop_n(3);
+        assert!(batch.len() == 3);
+        assert!(batch.next() == Some(40));
+        assert!(heap.peek() == Some(&45));
+        assert!(heap.pop_n(100).len() == 11);
+        assert!(heap.pop_until(1000).count() == 11);
+        assert!(heap.pop_n(1).next().is_none());
     }
 }
@@ -0,0 +1,260 @@
+//! A generational slab with compact `u32` slots, used as node storage by the heaps.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::num::NonZeroU32;
+use core::ops;
+
+/// The position of a node in a [`Slab`](./struct.Slab.html).
+///
+/// Stored as `position + 1` so that `Option<Slot>` is as small as a `u32`, which keeps the links
+/// between nodes compact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct Slot(NonZeroU32);
+
+impl Slot {
+    pub(crate) fn new(position: usize) -> Slot {
+        u32::try_from(position)
+            .ok()
+            .and_then(|position| NonZeroU32::new(position.wrapping_add(1)))
+            .map(Slot)
+            .expect("A heap can not hold more than `u32::MAX - 1` nodes.")
+    }
+
+    pub(crate) fn position(self) -> usize {
+        (self.0.get() - 1) as usize
+    }
+}
+
+/// A handle to an element in a heap, as returned by `push`.
+///
+/// Consists of the slot of the element and the generation of that slot, so a handle to a removed
+/// element is detected as stale even after its slot has been reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Handle {
+    slot: Slot,
+    generation: NonZeroU32,
+}
+
+impl Handle {
+    pub(crate) fn new(slot: Slot, generation: NonZeroU32) -> Handle {
+        Handle { slot, generation }
+    }
+
+    pub(crate) fn slot(self) -> Slot {
+        self.slot
+    }
+
+    pub(crate) fn generation(self) -> NonZeroU32 {
+        self.generation
+    }
+}
+
+pub(crate) fn next_generation(generation: NonZeroU32) -> NonZeroU32 {
+    NonZeroU32::new(generation.get().wrapping_add(1)).unwrap_or(NonZeroU32::MIN)
+}
+
+#[derive(Clone, Debug)]
+enum Entry<T> {
+    Occupied {
+        generation: NonZeroU32,
+        value: T,
+    },
+    Vacant {
+        generation: NonZeroU32,
+        next_free: Option<Slot>,
+    },
+}
+
+impl<T> Entry<T> {
+    fn generation(&self) -> NonZeroU32 {
+        match self {
+            Entry::Occupied { generation, .. } | Entry::Vacant { generation, .. } => *generation,
+        }
+    }
+}
+
+/// Storage that hands out a stable `Slot` for every inserted value and reuses the slots of
+/// removed values.
+#[derive(Clone, Debug)]
+pub(crate) struct Slab<T> {
+    entries: Vec<Entry<T>>,
+    free_head: Option<Slot>,
+    len: usize,
+}
+
+impl<T> Slab<T> {
+    pub(crate) fn with_capacity(n: usize) -> Slab<T> {
+        Slab {
+            entries: Vec::with_capacity(n),
+            free_head: None,
+            len: 0,
+        }
+    }
+
+    /// The number of occupied slots.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    #[cfg(test)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.entries.capacity()
+    }
+
+    /// One past the highest position that was ever occupied.
+    pub(crate) fn end(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The slot at `position` if it is occupied.
+    pub(crate) fn occupied(&self, position: usize) -> Option<Slot> {
+        match self.entries.get(position) {
+            Some(Entry::Occupied { .. }) => Some(Slot::new(position)),
+            _ => None,
+        }
+    }
+
+    /// Remove all values while keeping the allocation. Outstanding handles become stale.
+    pub(crate) fn clear(&mut self) {
+        let count = self.entries.len();
+        for (position, entry) in self.entries.iter_mut().enumerate() {
+            *entry = Entry::Vacant {
+                generation: next_generation(entry.generation()),
+                next_free: if position + 1 < count {
+                    Some(Slot::new(position + 1))
+                } else {
+                    None
+                },
+            };
+        }
+        self.free_head = if count > 0 { Some(Slot::new(0)) } else { None };
+        self.len = 0;
+    }
+
+    pub(crate) fn insert(&mut self, value: T) -> Slot {
+        self.len += 1;
+        match self.free_head {
+            Some(slot) => {
+                let entry = &mut self.entries[slot.position()];
+                let (generation, next_free) = match *entry {
+                    Entry::Vacant {
+                        generation,
+                        next_free,
+                    } => (generation, next_free),
+                    Entry::Occupied { .. } => unreachable!("The free list only has vacant slots."),
+                };
+                *entry = Entry::Occupied { generation, value };
+                self.free_head = next_free;
+                slot
+            }
+            None => {
+                let slot = Slot::new(self.entries.len());
+                self.entries.push(Entry::Occupied {
+                    generation: NonZeroU32::MIN,
+                    value,
+                });
+                slot
+            }
+        }
+    }
+
+    /// Remove the value at `slot`, returning it if the slot was occupied.
+    pub(crate) fn remove(&mut self, slot: Slot) -> Option<T> {
+        let entry = self.entries.get_mut(slot.position())?;
+        if let Entry::Vacant { .. } = entry {
+            return None;
+        }
+        let vacant = Entry::Vacant {
+            generation: next_generation(entry.generation()),
+            next_free: self.free_head,
+        };
+        self.free_head = Some(slot);
+        self.len -= 1;
+        match core::mem::replace(entry, vacant) {
+            Entry::Occupied { value, .. } => Some(value),
+            Entry::Vacant { .. } => unreachable!("Checked above."),
+        }
+    }
+
+    /// Look up the slot of `handle`, or `None` if the handle is stale.
+    pub(crate) fn slot(&self, handle: Handle) -> Option<Slot> {
+        match self.entries.get(handle.slot.position()) {
+            Some(Entry::Occupied { generation, .. }) if *generation == handle.generation => {
+                Some(handle.slot)
+            }
+            _ => None,
+        }
+    }
+
+    /// The handle of the value at the occupied `slot`.
+    pub(crate) fn handle(&self, slot: Slot) -> Handle {
+        Handle {
+            slot,
+            generation: self.entries[slot.position()].generation(),
+        }
+    }
+}
+
+impl<T> ops::Index<Slot> for Slab<T> {
+    type Output = T;
+
+    fn index(&self, slot: Slot) -> &T {
+        match &self.entries[slot.position()] {
+            Entry::Occupied { value, .. } => value,
+            Entry::Vacant { .. } => panic!("Should only access occupied slots."),
+        }
+    }
+}
+
+impl<T> ops::IndexMut<Slot> for Slab<T> {
+    fn index_mut(&mut self, slot: Slot) -> &mut T {
+        match &mut self.entries[slot.position()] {
+            Entry::Occupied { value, .. } => value,
+            Entry::Vacant { .. } => panic!("Should only access occupied slots."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Handle, Slab, Slot};
+    use core::mem::size_of;
+
+    #[test]
+    fn links_and_handles_are_compact() {
+        assert!(size_of::<Option<Slot>>() == 4);
+        assert!(size_of::<Option<Handle>>() == 8);
+    }
+
+    #[test]
+    fn stale_handles_are_detected() {
+        let mut slab = Slab::with_capacity(1);
+        let first = slab.insert(1);
+        let handle = slab.handle(first);
+        assert!(slab.slot(handle) == Some(first));
+        assert!(slab.remove(first) == Some(1));
+        assert!(slab.remove(first).is_none());
+        let second = slab.insert(2);
+        assert!(second == first);
+        assert!(slab.slot(handle).is_none());
+        assert!(slab[second] == 2);
+    }
+
+    #[test]
+    fn clear_keeps_capacity_and_invalidates_handles() {
+        let mut slab = Slab::with_capacity(4);
+        let slot = slab.insert(1);
+        let handle = slab.handle(slot);
+        slab.insert(2);
+        slab.clear();
+        assert!(slab.len() == 0);
+        assert!(slab.capacity() == 4);
+        assert!(slab.slot(handle).is_none());
+        slab.insert(3);
+        slab.insert(4);
+        slab.insert(5);
+        assert!(slab.len() == 3);
+        assert!(slab.capacity() == 4);
+    }
+}
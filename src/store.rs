@@ -0,0 +1,351 @@
+//! Node storage of the `HollowHeap`.
+//!
+//! The nodes are stored in a struct-of-arrays layout by default. The `interleaved_layout` feature
+//! keeps each item next to its key and links instead, which suits small items better. As Cargo
+//! unifies features, the feature switches the layout for every crate in the build.
+
+use alloc::vec::Vec;
+use core::ops;
+
+use crate::slab::{Slab, Slot};
+use crate::Handle;
+
+/// Per-node data indexed by `Slot`, parallel to the item slab.
+#[derive(Clone, Debug)]
+pub(crate) struct Column<T>(Vec<T>);
+
+impl<T> Column<T> {
+    fn with_capacity(n: usize) -> Column<T> {
+        Column(Vec::with_capacity(n))
+    }
+
+    /// Store `value` for a freshly inserted `slot`, growing the column if necessary.
+    fn set(&mut self, slot: Slot, value: T) {
+        let position = slot.position();
+        if position == self.0.len() {
+            self.0.push(value);
+        } else {
+            self.0[position] = value;
+        }
+    }
+}
+
+impl<T> ops::Index<Slot> for Column<T> {
+    type Output = T;
+
+    fn index(&self, slot: Slot) -> &T {
+        &self.0[slot.position()]
+    }
+}
+
+impl<T> ops::IndexMut<Slot> for Column<T> {
+    fn index_mut(&mut self, slot: Slot) -> &mut T {
+        &mut self.0[slot.position()]
+    }
+}
+
+/// The rank and the links of a node, which are always accessed together.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Links {
+    pub(crate) child: Option<Slot>,
+    pub(crate) next: Option<Slot>,
+    pub(crate) second_parent: Option<Slot>,
+    /// Quote from the [hollow heap paper](https://arxiv.org/abs/1510.06535):
+    /// > The rank of a node in a [...] hollow heap of `N` nodes is at most `logφN` (logarithm of N
+    /// > with the golden ratio)
+    ///
+    /// This means that the rank in a hollow heap whose size is limited by `usize` is never greater
+    /// than `logφ(usize::max_value) = 92.18688578640361` and thus fits in a `u8`.
+    pub(crate) rank: u8,
+}
+
+impl Links {
+    pub(crate) const NONE: Links = Links {
+        child: None,
+        next: None,
+        second_parent: None,
+        rank: 0,
+    };
+}
+
+/// The key, item and links of the nodes, stored apart from each other.
+///
+/// `link`, `ranked_link` and the consolidation in `delete` only look at keys, ranks and links, so
+/// those are kept in dense parallel columns while the items live in a separate slab. That way
+/// large items do not dilute the cache during consolidation.
+#[cfg(not(feature = "interleaved_layout"))]
+#[derive(Clone, Debug)]
+struct Nodes<K, V> {
+    items: Slab<Option<V>>,
+    keys: Column<Option<K>>,
+    links: Column<Links>,
+}
+
+#[cfg(not(feature = "interleaved_layout"))]
+impl<K, V> Nodes<K, V> {
+    fn with_capacity(n: usize) -> Nodes<K, V> {
+        Nodes {
+            items: Slab::with_capacity(n),
+            keys: Column::with_capacity(n),
+            links: Column::with_capacity(n),
+        }
+    }
+
+    fn slab(&self) -> &Slab<Option<V>> {
+        &self.items
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        for key in self.keys.0.iter_mut() {
+            *key = None;
+        }
+    }
+
+    fn insert(&mut self, item: V, key: K) -> Slot {
+        let slot = self.items.insert(Some(item));
+        self.keys.set(slot, Some(key));
+        self.links.set(slot, Links::NONE);
+        slot
+    }
+
+    /// Remove the node at `slot`, returning whether it was full and its item.
+    fn remove(&mut self, slot: Slot) -> (bool, Option<V>) {
+        let full = self.keys[slot].take().is_some();
+        (full, self.items.remove(slot).flatten())
+    }
+
+    fn key(&self, slot: Slot) -> &Option<K> {
+        &self.keys[slot]
+    }
+
+    fn key_mut(&mut self, slot: Slot) -> &mut Option<K> {
+        &mut self.keys[slot]
+    }
+
+    fn item(&self, slot: Slot) -> &Option<V> {
+        &self.items[slot]
+    }
+
+    fn item_mut(&mut self, slot: Slot) -> &mut Option<V> {
+        &mut self.items[slot]
+    }
+
+    fn links(&self, slot: Slot) -> &Links {
+        &self.links[slot]
+    }
+
+    fn links_mut(&mut self, slot: Slot) -> &mut Links {
+        &mut self.links[slot]
+    }
+}
+
+/// The key, item and links of one node, stored next to each other.
+#[cfg(feature = "interleaved_layout")]
+#[derive(Clone, Debug)]
+struct Node<K, V> {
+    key: Option<K>,
+    item: Option<V>,
+    links: Links,
+}
+
+/// The nodes in a single slab, so that an item shares the cache line of its key and links.
+#[cfg(feature = "interleaved_layout")]
+#[derive(Clone, Debug)]
+struct Nodes<K, V> {
+    nodes: Slab<Node<K, V>>,
+}
+
+#[cfg(feature = "interleaved_layout")]
+impl<K, V> Nodes<K, V> {
+    fn with_capacity(n: usize) -> Nodes<K, V> {
+        Nodes {
+            nodes: Slab::with_capacity(n),
+        }
+    }
+
+    fn slab(&self) -> &Slab<Node<K, V>> {
+        &self.nodes
+    }
+
+    fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    fn insert(&mut self, item: V, key: K) -> Slot {
+        self.nodes.insert(Node {
+            key: Some(key),
+            item: Some(item),
+            links: Links::NONE,
+        })
+    }
+
+    /// Remove the node at `slot`, returning whether it was full and its item.
+    fn remove(&mut self, slot: Slot) -> (bool, Option<V>) {
+        match self.nodes.remove(slot) {
+            Some(node) => (node.key.is_some(), node.item),
+            None => (false, None),
+        }
+    }
+
+    fn key(&self, slot: Slot) -> &Option<K> {
+        &self.nodes[slot].key
+    }
+
+    fn key_mut(&mut self, slot: Slot) -> &mut Option<K> {
+        &mut self.nodes[slot].key
+    }
+
+    fn item(&self, slot: Slot) -> &Option<V> {
+        &self.nodes[slot].item
+    }
+
+    fn item_mut(&mut self, slot: Slot) -> &mut Option<V> {
+        &mut self.nodes[slot].item
+    }
+
+    fn links(&self, slot: Slot) -> &Links {
+        &self.nodes[slot].links
+    }
+
+    fn links_mut(&mut self, slot: Slot) -> &mut Links {
+        &mut self.nodes[slot].links
+    }
+}
+
+/// The nodes of a hollow heap, indexed by `Slot` for their links.
+///
+/// A node is hollow if it has neither a key nor an item. Links of vacant slots are stale and only
+/// reset once the slot is reused.
+#[derive(Clone, Debug)]
+pub(crate) struct Dag<K, V> {
+    nodes: Nodes<K, V>,
+    /// Insertion sequence numbers, only maintained by stable heaps.
+    sequences: Column<u64>,
+    /// The number of hollow nodes.
+    hollow: usize,
+}
+
+impl<K, V> Dag<K, V> {
+    pub(crate) fn new() -> Dag<K, V> {
+        Dag::with_capacity(0)
+    }
+
+    pub(crate) fn with_capacity(n: usize) -> Dag<K, V> {
+        Dag {
+            nodes: Nodes::with_capacity(n),
+            sequences: Column::with_capacity(0),
+            hollow: 0,
+        }
+    }
+
+    /// The number of nodes, including hollow ones.
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.slab().len()
+    }
+
+    /// The number of hollow nodes.
+    pub(crate) fn hollow_len(&self) -> usize {
+        self.hollow
+    }
+
+    /// The number of full nodes, i.e. the number of elements in the heap.
+    pub(crate) fn full_len(&self) -> usize {
+        self.len() - self.hollow
+    }
+
+    #[cfg(test)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.nodes.slab().capacity()
+    }
+
+    /// Remove all nodes while keeping the allocations. Outstanding handles become stale.
+    pub(crate) fn clear(&mut self) {
+        self.nodes.clear();
+        self.hollow = 0;
+    }
+
+    pub(crate) fn insert(&mut self, item: V, key: K) -> Slot {
+        self.nodes.insert(item, key)
+    }
+
+    /// Remove the node at `slot`, returning its item unless it was hollow.
+    pub(crate) fn remove(&mut self, slot: Slot) -> Option<V> {
+        let (full, item) = self.nodes.remove(slot);
+        if !full {
+            self.hollow -= 1;
+        }
+        item
+    }
+
+    /// Turn the node at `slot` hollow, returning its item and key unless it already was hollow.
+    pub(crate) fn make_hollow(&mut self, slot: Slot) -> Option<(V, K)> {
+        let key = self.nodes.key_mut(slot).take()?;
+        let item = self
+            .nodes
+            .item_mut(slot)
+            .take()
+            .expect("Full nodes have both an item and a key.");
+        self.hollow += 1;
+        Some((item, key))
+    }
+
+    pub(crate) fn is_hollow(&self, slot: Slot) -> bool {
+        self.nodes.key(slot).is_none()
+    }
+
+    /// The key of the full node at `slot`.
+    pub(crate) fn key(&self, slot: Slot) -> &K {
+        self.nodes
+            .key(slot)
+            .as_ref()
+            .expect("Should only access the key of full nodes.")
+    }
+
+    pub(crate) fn key_mut(&mut self, slot: Slot) -> &mut K {
+        self.nodes
+            .key_mut(slot)
+            .as_mut()
+            .expect("Should only access the key of full nodes.")
+    }
+
+    pub(crate) fn item(&self, slot: Slot) -> Option<&V> {
+        self.nodes.item(slot).as_ref()
+    }
+
+    pub(crate) fn item_mut(&mut self, slot: Slot) -> Option<&mut V> {
+        self.nodes.item_mut(slot).as_mut()
+    }
+
+    pub(crate) fn sequence(&self, slot: Slot) -> u64 {
+        self.sequences[slot]
+    }
+
+    pub(crate) fn set_sequence(&mut self, slot: Slot, sequence: u64) {
+        self.sequences.set(slot, sequence);
+    }
+
+    /// Look up the slot of `handle`, or `None` if the handle is stale.
+    pub(crate) fn slot(&self, handle: Handle) -> Option<Slot> {
+        self.nodes.slab().slot(handle)
+    }
+
+    /// The handle of the node at the occupied `slot`.
+    pub(crate) fn handle(&self, slot: Slot) -> Handle {
+        self.nodes.slab().handle(slot)
+    }
+}
+
+impl<K, V> ops::Index<Slot> for Dag<K, V> {
+    type Output = Links;
+
+    fn index(&self, slot: Slot) -> &Links {
+        self.nodes.links(slot)
+    }
+}
+
+impl<K, V> ops::IndexMut<Slot> for Dag<K, V> {
+    fn index_mut(&mut self, slot: Slot) -> &mut Links {
+        self.nodes.links_mut(slot)
+    }
+}
@@ -0,0 +1,45 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use hollow_heap::HollowHeap;
+
+struct CountingAllocator;
+
+thread_local! {
+    // Only count allocations of the current thread, the test harness allocates concurrently.
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn steady_state_pop_does_not_allocate() {
+    let mut heap: HollowHeap<u32, u32> = HollowHeap::with_capacity(1000);
+    for num in 0..900 {
+        heap.push((num * 7919) % 1000);
+    }
+    // the first pop sets up the scratch space
+    heap.pop();
+
+    let before = ALLOCATIONS.with(Cell::get);
+    for num in 0..500 {
+        let index = heap.push(num + 2000);
+        heap.change_key(index, num);
+        heap.pop();
+        heap.pop();
+    }
+    while heap.pop().is_some() {}
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+}
@@ -6,7 +6,7 @@ use std::collections::HashMap;
 
 use proptest::collection::vec;
 
-use hollow_heap::HollowHeap;
+use hollow_heap::{FixedHollowHeap, HeapForest, HollowHeap, HollowHeapBuilder};
 
 proptest! {
 
@@ -15,7 +15,7 @@ proptest! {
         let mut heap = HollowHeap::max_heap();
         heap.push(num);
         assert!(heap.pop() == Some(num));
-        assert!(heap.pop() == None);
+        assert!(heap.pop().is_none());
     }
 
     #[test]
@@ -55,7 +55,7 @@ proptest! {
         let value = *heap.peek().unwrap();
         heap.change_key(index, value + 1);
         heap.delete(second_index);
-        while heap.pop() != None {}
+        while heap.pop().is_some() {}
     }
 
     #[test]
@@ -75,7 +75,7 @@ proptest! {
                 heap.delete(*idx);
             }
         }
-        while heap.pop() != None {}
+        while heap.pop().is_some() {}
     }
 
     #[test]
@@ -98,6 +98,354 @@ proptest! {
                 heap.delete(*idx);
             }
         }
-        while heap.pop() != None {}
+        while heap.pop().is_some() {}
+    }
+
+    #[test]
+    fn fixed_heap_pops_sorted_vec_with_delete_and_change_key(vector in vec(0..10000u32, 2..64)) {
+        let mut heap: FixedHollowHeap<u32, u32, 128> = FixedHollowHeap::min_heap();
+        let mut indices = Vec::new();
+        for num in vector.iter() {
+            indices.push(heap.push(*num).unwrap());
+        }
+        let mut expected = Vec::new();
+        for (i, (index, val)) in indices.into_iter().zip(vector.iter()).enumerate() {
+            if i % 3 == 0 && *val > 0 {
+                prop_assert!(heap.change_key(index, val / 2).is_ok());
+                expected.push(*val);
+            } else if i % 3 != 1 {
+                expected.push(*val);
+            } else {
+                heap.delete(index);
+            }
+        }
+        let mut popped = Vec::new();
+        while let Some(num) = heap.pop() {
+            popped.push(num);
+        }
+        expected.sort();
+        popped.sort();
+        prop_assert_eq!(popped, expected);
+    }
+
+    #[test]
+    fn one_parent_heap_pops_sorted_vec_with_change_item(vector in vec(1..10000u32, 2..1000)) {
+        let mut heap = HollowHeapBuilder::new_with_value_is_key().one_parent().build();
+        let mut indices = Vec::new();
+        for num in vector.iter() {
+            indices.push(heap.push(*num));
+        }
+        // pop a sentinel so that the remaining nodes are consolidated into trees with children
+        heap.push(0);
+        prop_assert_eq!(heap.pop(), Some(0));
+        let mut expected = Vec::new();
+        for (i, (index, val)) in indices.into_iter().zip(vector.iter()).enumerate() {
+            if i % 2 == 0 {
+                heap.change_item(index, val / 2);
+                expected.push(val / 2);
+            } else {
+                expected.push(*val);
+            }
+        }
+        let mut popped = Vec::new();
+        while let Some(num) = heap.pop() {
+            popped.push(num);
+        }
+        expected.sort();
+        prop_assert_eq!(popped, expected);
+    }
+
+    #[test]
+    fn lazy_linking_pops_in_the_same_order(vector in vec(1..10000u32, 2..500)) {
+        let mut eager = HollowHeapBuilder::new_with_value_is_key().build();
+        let mut lazy = HollowHeapBuilder::new_with_value_is_key().lazy_linking(true).build();
+        let mut lazy_one_parent = HollowHeapBuilder::new_with_value_is_key()
+            .lazy_linking(true)
+            .one_parent()
+            .build();
+        let mut popped = [Vec::new(), Vec::new(), Vec::new()];
+        for (heap, popped) in [&mut eager, &mut lazy, &mut lazy_one_parent]
+            .iter_mut()
+            .zip(popped.iter_mut())
+        {
+            let mut indices = Vec::new();
+            for num in vector.iter() {
+                indices.push((heap.push(*num), *num));
+            }
+            // pop a sentinel so that the remaining nodes are consolidated
+            heap.push(0);
+            popped.push(heap.pop());
+            for (i, (index, val)) in indices.into_iter().enumerate() {
+                match i % 3 {
+                    0 => {
+                        heap.change_item(index, val / 3);
+                    }
+                    1 => {
+                        heap.delete(index);
+                    }
+                    _ => {
+                        heap.push(val + 1);
+                    }
+                }
+            }
+            for _ in 0..vector.len() / 2 {
+                popped.push(heap.pop());
+            }
+            for num in vector.iter() {
+                heap.push(num / 2);
+            }
+            while let Some(num) = heap.pop() {
+                popped.push(Some(num));
+            }
+        }
+        prop_assert_eq!(&popped[0], &popped[1]);
+        prop_assert_eq!(&popped[0], &popped[2]);
+    }
+
+    #[test]
+    fn lazy_linking_pops_equal_keys_like_eager_linking(
+        keys in vec(0..4u32, 2..100),
+        one_parent in any::<bool>(),
+    ) {
+        // which of the elements with equal keys comes first is only fixed for stable heaps
+        let mut popped = [Vec::new(), Vec::new()];
+        for (lazy, popped) in [false, true].iter().zip(popped.iter_mut()) {
+            let mut builder = HollowHeapBuilder::new(|job: &(u32, usize)| job.0);
+            builder.lazy_linking(*lazy).stable();
+            if one_parent {
+                builder.one_parent();
+            }
+            let mut heap = builder.build();
+            let handles: Vec<_> = keys
+                .iter()
+                .enumerate()
+                .map(|(id, key)| heap.push((*key, id)))
+                .collect();
+            popped.push(heap.pop());
+            for (id, (handle, key)) in handles.into_iter().zip(keys.iter()).enumerate() {
+                match id % 4 {
+                    0 => {
+                        let _ = heap.try_change_key(handle, key / 2);
+                    }
+                    1 => {
+                        heap.delete(handle);
+                    }
+                    _ => {
+                        heap.push((*key, keys.len() + id));
+                    }
+                }
+            }
+            for _ in 0..keys.len() / 4 {
+                popped.push(heap.pop());
+            }
+            for (id, key) in keys.iter().enumerate() {
+                heap.push((*key, 2 * keys.len() + id));
+            }
+            while let Some(job) = heap.pop() {
+                popped.push(Some(job));
+            }
+        }
+        prop_assert_eq!(&popped[0], &popped[1]);
+    }
+
+    #[test]
+    fn stable_heap_pops_like_a_stable_sort(vector in vec(1..20u32, 2..500), lazy in any::<bool>()) {
+        let mut heap = HollowHeapBuilder::new(|job: &(u32, usize)| job.0)
+            .stable()
+            .lazy_linking(lazy)
+            .build();
+        let mut indices = Vec::new();
+        for (i, num) in vector.iter().enumerate() {
+            indices.push(heap.push((*num, i)));
+        }
+        heap.push((0, 0));
+        prop_assert_eq!(heap.pop(), Some((0, 0)));
+        let mut expected = Vec::new();
+        for (i, (index, num)) in indices.into_iter().zip(vector.iter()).enumerate() {
+            if i % 3 == 0 {
+                heap.change_item(index, (num / 2, i));
+                expected.push((num / 2, i));
+            } else {
+                expected.push((*num, i));
+            }
+        }
+        expected.sort_by_key(|job| job.0);
+        let popped: Vec<_> = heap.into_iter().collect();
+        prop_assert_eq!(popped, expected);
+    }
+
+    #[test]
+    fn iter_sorted_matches_pop_order(
+        vector in vec(1..10000u32, 2..500),
+        one_parent in any::<bool>(),
+        lazy in any::<bool>(),
+        rebuild in any::<bool>(),
+    ) {
+        let mut builder = HollowHeapBuilder::new_with_value_is_key();
+        builder.lazy_linking(lazy);
+        if one_parent {
+            builder.one_parent();
+        }
+        if !rebuild {
+            builder.without_rebuild();
+        }
+        let mut heap = builder.build();
+        let mut indices = Vec::new();
+        for num in vector.iter() {
+            indices.push((heap.push(*num), *num));
+        }
+        heap.push(0);
+        heap.pop();
+        for (i, (index, val)) in indices.into_iter().enumerate() {
+            match i % 4 {
+                0 => {
+                    // the element may have been popped already
+                    let _ = heap.try_change_item(index, val / 3);
+                }
+                1 => {
+                    heap.delete(index);
+                }
+                2 => {
+                    heap.push(val + 1);
+                }
+                _ => {}
+            }
+            if i % 50 == 49 {
+                heap.pop();
+            }
+        }
+        let sorted: Vec<_> = heap.iter_sorted().copied().collect();
+        prop_assert_eq!(heap.iter_sorted().len(), sorted.len());
+        prop_assert_eq!(heap.nth_best(sorted.len() / 2), sorted.get(sorted.len() / 2));
+        let popped: Vec<_> = heap.into_iter().collect();
+        prop_assert_eq!(sorted, popped);
+    }
+
+    #[test]
+    fn change_keys_pops_like_single_changes(
+        vector in vec(1..10000u32, 2..500),
+        one_parent in any::<bool>(),
+        lazy in any::<bool>(),
+    ) {
+        let mut builder = HollowHeapBuilder::new(|pair: &(u32, usize)| pair.0);
+        builder.lazy_linking(lazy);
+        if one_parent {
+            builder.one_parent();
+        }
+        let mut heap = builder.build();
+        let mut indices = Vec::new();
+        for (i, num) in vector.iter().enumerate() {
+            indices.push(heap.push((*num, i)));
+        }
+        heap.push((0, 0));
+        heap.pop();
+        let mut expected: Vec<_> = vector.iter().copied().zip(0..).collect();
+        let mut changes = Vec::new();
+        for (i, index) in indices.into_iter().enumerate() {
+            if i % 3 != 1 {
+                changes.push((index, vector[i] / 2));
+                expected[i].0 = vector[i] / 2;
+            }
+        }
+        let batch_len = changes.len();
+        prop_assert_eq!(heap.change_keys(changes).unwrap().len(), batch_len);
+        expected.sort();
+        let mut popped = Vec::new();
+        while let Some(key) = heap.peek_key() {
+            popped.push((key, heap.pop().unwrap().1));
+        }
+        prop_assert!(popped.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        popped.sort();
+        prop_assert_eq!(popped, expected);
+    }
+
+    #[test]
+    fn update_with_pops_sorted_vec(
+        vector in vec((1..10000u32, 1..10000u32), 2..500),
+        lazy in any::<bool>(),
+    ) {
+        let mut builder = HollowHeapBuilder::new(|pair: &(u32, usize)| pair.0);
+        builder.lazy_linking(lazy);
+        let mut heap = builder.build();
+        let mut indices = Vec::new();
+        for (i, (num, _)) in vector.iter().enumerate() {
+            indices.push(heap.push((*num, i)));
+        }
+        heap.push((0, 0));
+        heap.pop();
+        for (index, (_, new_num)) in indices.into_iter().zip(vector.iter()) {
+            heap.update_with(index, |pair| pair.0 = *new_num).unwrap();
+        }
+        let mut expected: Vec<_> = vector.iter().map(|(_, new_num)| *new_num).collect();
+        expected.sort();
+        let popped: Vec<_> = heap.into_iter().map(|pair| pair.0).collect();
+        prop_assert_eq!(popped, expected);
+    }
+
+    #[test]
+    fn forest_keeps_offsets_when_melding(
+        operations in vec((0..5u8, 0..8usize, 0..8usize, 0..1000u32), 1..300),
+    ) {
+        let mut forest = HeapForest::new(|lhs, rhs| lhs < rhs, |pair: &(u32, usize)| pair.0);
+        // the id, offset and elements of every heap, offsets being lower bounds of the keys
+        let mut heaps: Vec<_> = (0..4).map(|_| (forest.new_heap(), 0, Vec::new())).collect();
+        for (i, (operation, lhs, rhs, num)) in operations.into_iter().enumerate() {
+            let (mut lhs, rhs) = (lhs % heaps.len(), rhs % heaps.len());
+            match operation {
+                0 => {
+                    let key = heaps[lhs].1 + num;
+                    let handle = forest.push(heaps[lhs].0, (key, i));
+                    heaps[lhs].2.push((key, handle, i));
+                }
+                1 => {
+                    let delta = num % 50;
+                    forest.add_to_all_keys(heaps[lhs].0, delta);
+                    heaps[lhs].1 += delta;
+                    for element in heaps[lhs].2.iter_mut() {
+                        element.0 += delta;
+                    }
+                }
+                2 if lhs != rhs => {
+                    let (other, offset, elements) = heaps.remove(rhs);
+                    if rhs < lhs {
+                        lhs -= 1;
+                    }
+                    forest.meld(heaps[lhs].0, other);
+                    heaps[lhs].1 = heaps[lhs].1.min(offset);
+                    heaps[lhs].2.extend(elements);
+                }
+                3 => {
+                    let best = heaps[lhs].2.iter().map(|element| element.0).min();
+                    let popped = forest.pop_entry(heaps[lhs].0);
+                    prop_assert_eq!(popped.map(|(key, _)| key), best);
+                    if let Some((_, (_, id))) = popped {
+                        let position = heaps[lhs].2.iter().position(|element| element.2 == id);
+                        heaps[lhs].2.swap_remove(position.unwrap());
+                    }
+                }
+                4 if !heaps[lhs].2.is_empty() => {
+                    let (heap, offset, elements) = &mut heaps[lhs];
+                    let position = num as usize % elements.len();
+                    let element = &mut elements[position];
+                    let new_key = *offset + (element.0 - *offset) / 2;
+                    if new_key < element.0 {
+                        element.1 = forest.change_key(*heap, element.1, new_key);
+                        element.0 = new_key;
+                    }
+                }
+                _ => {}
+            }
+            let best = heaps[lhs].2.iter().map(|element| element.0).min();
+            prop_assert_eq!(forest.peek_key(heaps[lhs].0), best);
+        }
+        for (heap, _, elements) in heaps {
+            let mut expected: Vec<_> = elements.into_iter().map(|element| element.0).collect();
+            expected.sort();
+            let popped: Vec<_> = (0..expected.len())
+                .filter_map(|_| forest.pop_entry(heap).map(|(key, _)| key))
+                .collect();
+            prop_assert_eq!(popped, expected);
+            prop_assert!(forest.is_empty(heap));
+        }
     }
 }